@@ -0,0 +1,192 @@
+use crate::{
+    correlate, diff,
+    id::{devices::NetworkDevice, fingerprint::FingerprintDb},
+    output,
+    response::CapturedResponse,
+    scanner::{confirm_reported, ScanResult},
+    target::Target,
+    ReidentifyArgs,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, net::IpAddr};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+/// A line of an archive, responses are written as they come in and the results once the scan is done
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Response {
+        target: Target,
+        response: Box<CapturedResponse>,
+    },
+    Result(Box<ScanResult>),
+}
+
+/// Writes every response of a scan and what it was identified as to an ndjson file
+pub struct Recorder {
+    file: File,
+    responses: usize,
+}
+
+impl Recorder {
+    pub async fn create(path: &str) -> Result<Self> {
+        let file = File::create(path)
+            .await
+            .with_context(|| format!("failed to create {path}"))?;
+
+        Ok(Self { file, responses: 0 })
+    }
+
+    /// Takes the responses out of the result, they're only kept around for this
+    pub async fn responses(&mut self, r: &mut ScanResult) -> Result<()> {
+        for (target, response) in std::mem::take(&mut r.responses) {
+            self.write(&Record::Response {
+                target,
+                response: Box::new(response),
+            })
+            .await?;
+            self.responses += 1;
+        }
+
+        Ok(())
+    }
+
+    pub async fn results(mut self, results: &[ScanResult]) -> Result<usize> {
+        for r in results {
+            self.write(&Record::Result(Box::new(r.clone()))).await?;
+        }
+
+        self.file.flush().await?;
+        Ok(self.responses)
+    }
+
+    async fn write(&mut self, record: &Record) -> Result<()> {
+        let line = format!("{}\n", serde_json::to_string(record)?);
+        self.file.write_all(line.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+/// Everything an archive holds about one ip
+#[derive(Default)]
+pub struct Host {
+    pub responses: Vec<(Target, CapturedResponse)>,
+    /// missing when the recording was interrupted
+    pub result: Option<ScanResult>,
+}
+
+impl Host {
+    /// The response the scan identified the host by, with the certificate of any other port if it had none
+    pub fn primary(&self) -> Option<(Target, CapturedResponse)> {
+        // an interrupted recording has no results, the first response is what the scan would have used
+        let (target, response) = self
+            .result
+            .as_ref()
+            .and_then(|r| self.responses.iter().find(|(t, _)| *t == r.target))
+            .or_else(|| self.responses.first())?;
+
+        let mut response = response.clone();
+        if response.cert.is_none() {
+            response.cert = self.responses.iter().find_map(|(_, r)| r.cert.clone());
+        }
+
+        Some((*target, response))
+    }
+}
+
+/// Every host of an archive, by ip
+pub async fn read(path: &str) -> Result<BTreeMap<IpAddr, Host>> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read {path}"))?;
+
+    let mut hosts = BTreeMap::<IpAddr, Host>::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = serde_json::from_str::<Record>(line)
+            .with_context(|| format!("invalid record on line {} of {path}", i + 1))?;
+        match record {
+            Record::Response { target, response } => hosts
+                .entry(target.ip)
+                .or_default()
+                .responses
+                .push((target, *response)),
+            Record::Result(r) => {
+                let ip = r.ip;
+                hosts.entry(ip).or_default().result = Some(*r);
+            }
+        }
+    }
+
+    Ok(hosts)
+}
+
+pub async fn reidentify(args: ReidentifyArgs) -> Result<()> {
+    let fingerprints = FingerprintDb::with_file(args.fingerprints.as_deref())?;
+    let hosts = read(&args.archive).await?;
+
+    let mut original = vec![];
+    let mut results = vec![];
+    for (ip, host) in hosts {
+        let Some((target, response)) = host.primary() else {
+            continue;
+        };
+
+        let id = fingerprints.identify(&ip, &response);
+        let mut fallback = id.best().is_some_and(|c| c.fingerprint.fallback);
+
+        let mut r = match host.result {
+            Some(r) => {
+                original.push(r.clone());
+                r
+            }
+            None => ScanResult {
+                open: host.responses.iter().map(|(t, _)| *t).collect(),
+                response_hash: response.body_hash(),
+                cert: response.cert.clone(),
+                favicon: response.favicon.clone(),
+                web_hostname: response.hostname(),
+                ..ScanResult::new(ip, target, NetworkDevice::Unidentified)
+            },
+        };
+
+        r.device = confirm_reported(
+            NetworkDevice::from_identification(&id),
+            &mut fallback,
+            r.ipp.as_ref(),
+            r.snmp.as_ref(),
+            r.pjl.as_ref(),
+        );
+        if args.explain {
+            println!("{ip}: {} on {}\n{id}", r.device, target.url());
+        }
+
+        results.push(r);
+    }
+
+    println!(
+        "Re-identified {} hosts from {} without touching the network",
+        results.len(),
+        args.archive
+    );
+
+    // compared per ip, the same way the recording was made
+    let before = original.iter().map(diff::Entry::from).collect::<Vec<_>>();
+    let after = results.iter().map(diff::Entry::from).collect::<Vec<_>>();
+
+    let results = correlate::merge(results);
+    output::write_results(&args.output, args.format, &results).await?;
+    println!("Successfully wrote to {}", args.output);
+
+    print!(
+        "-- Changes since the recording --\n{}",
+        diff::diff(&before, &after)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use crate::{
+    cert::CertInfo, favicon::Favicon, id::devices::NetworkDevice, response::CapturedResponse,
+    scanner::ScanResult,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+/// Enough for any login page, anything bigger is usually a firmware blob or a log
+const MAX_BODY: usize = 256 * 1024;
+
+const INDEX: &str = "index.tsv";
+
+/// Responses no fingerprint matched, one file per host and a tab separated index of all of them
+pub struct CaptureDir {
+    dir: PathBuf,
+}
+
+/// A file written by [`CaptureDir::save`]: `# key: value` lines, then `HTTP <status>`, the headers,
+/// an empty line and the body
+pub struct SavedResponse {
+    /// file name
+    pub name: String,
+    pub ip: IpAddr,
+    /// every `# key: value` line, including any added by hand
+    pub meta: Vec<(String, String)>,
+    /// only has the certificate and favicon fields rules can match on
+    pub response: CapturedResponse,
+}
+
+impl CaptureDir {
+    /// Creates the directory if missing and starts a new index
+    pub async fn create(dir: &str) -> Result<Self> {
+        let dir = PathBuf::from(dir);
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+
+        tokio::fs::write(
+            dir.join(INDEX),
+            "file\tip\turl\tstatus\ttitle\tserver\tcert_subject\tbody_size\tdevice\n",
+        )
+        .await?;
+
+        Ok(Self { dir })
+    }
+
+    pub async fn save(&self, r: &ScanResult, res: &CapturedResponse) -> Result<()> {
+        let t = &r.target;
+        let file = format!("{}_{}_{}.txt", t.ip, t.port, t.scheme).replace(':', "-");
+        let server = res.headers("server").next().unwrap_or_default();
+        let cert = res.cert.as_ref();
+
+        let mut body = res.body.as_str();
+        if body.len() > MAX_BODY {
+            let mut end = MAX_BODY;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body = &body[..end];
+        }
+
+        // ipp, snmp or pjl can still identify the device, which is a hint for the fingerprint
+        let device = match &r.device {
+            NetworkDevice::Identified(_) => r.device.to_string(),
+            NetworkDevice::Unidentified => String::new(),
+        };
+
+        // the same format as fingerprint fixtures, so adding an `# expect: <id>` line turns this into one
+        let mut content = format!(
+            "# ip: {}\n# url: {}\n# seen: {}\n",
+            t.ip,
+            t.url(),
+            r.seen_at.to_rfc3339()
+        );
+        if let Some(title) = &res.title {
+            content.push_str(&format!("# title: {title}\n"));
+        }
+        if let Some(cert) = cert {
+            content.push_str(&format!("# cert-subject: {}\n", cert.subject));
+            content.push_str(&format!("# cert-issuer: {}\n", cert.issuer));
+            for san in &cert.sans {
+                content.push_str(&format!("# cert-san: {san}\n"));
+            }
+            content.push_str(&format!("# cert: {cert}\n"));
+        }
+        if let Some(favicon) = &res.favicon {
+            content.push_str(&format!("# favicon-mmh3: {}\n", favicon.mmh3));
+            content.push_str(&format!("# favicon-sha256: {}\n", favicon.sha256));
+        }
+        if !device.is_empty() {
+            content.push_str(&format!("# identified by other probes as {device}\n"));
+        }
+        content.push_str(&format!(
+            "# body: {} bytes{}\n",
+            res.body.len(),
+            if body.len() < res.body.len() {
+                format!(", truncated to {}", body.len())
+            } else {
+                String::new()
+            }
+        ));
+
+        content.push_str(&format!("HTTP {}\n", res.status));
+        for (name, value) in &res.headers {
+            content.push_str(&format!("{name}: {value}\n"));
+        }
+        content.push('\n');
+        content.push_str(body);
+
+        tokio::fs::write(self.dir.join(&file), content).await?;
+
+        let clean = |s: &str| s.replace(['\t', '\n', '\r'], " ");
+        let line = [
+            file,
+            t.ip.to_string(),
+            t.url(),
+            res.status.to_string(),
+            clean(res.title.as_deref().unwrap_or_default()),
+            clean(server),
+            clean(cert.map(|c| c.subject.as_str()).unwrap_or_default()),
+            res.body.len().to_string(),
+            device,
+        ]
+        .join("\t");
+
+        OpenOptions::new()
+            .append(true)
+            .open(self.dir.join(INDEX))
+            .await?
+            .write_all(format!("{line}\n").as_bytes())
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl SavedResponse {
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let mut meta = vec![];
+
+        let mut lines = content.split_inclusive('\n');
+        let status = loop {
+            let line = lines.next().context("expected HTTP <status>")?.trim_end();
+            if let Some(status) = line.strip_prefix("HTTP ") {
+                break status.trim().parse::<u16>().context("invalid status")?;
+            }
+
+            if let Some((key, value)) = line
+                .strip_prefix('#')
+                .and_then(|m| m.trim_start().split_once(": "))
+            {
+                meta.push((key.to_owned(), value.trim().to_owned()));
+            }
+        };
+
+        let mut headers = vec![];
+        for line in lines.by_ref() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line.split_once(':').context("expected name: value")?;
+            headers.push((name.trim().to_lowercase(), value.trim().to_owned()));
+        }
+
+        let mut cert = None::<CertInfo>;
+        for (key, value) in &meta {
+            match key.as_str() {
+                "cert-subject" => cert
+                    .get_or_insert_with(blank_cert)
+                    .subject
+                    .clone_from(value),
+                "cert-issuer" => cert.get_or_insert_with(blank_cert).issuer.clone_from(value),
+                "cert-san" => cert.get_or_insert_with(blank_cert).sans.push(value.clone()),
+                _ => {}
+            }
+        }
+
+        let value = |key: &str| meta.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        // written by scans with --favicon, the mmh3 is the one rules usually use
+        let favicon = match value("favicon-mmh3") {
+            Some(mmh3) => Some(Favicon {
+                mmh3: mmh3.parse().context("invalid favicon-mmh3")?,
+                sha256: value("favicon-sha256").unwrap_or_default().to_owned(),
+            }),
+            None => None,
+        };
+
+        let ip = value("ip")
+            .context("missing # ip")?
+            .parse()
+            .context("invalid ip")?;
+
+        Ok(Self {
+            name: name.to_owned(),
+            ip,
+            meta,
+            response: CapturedResponse {
+                cert,
+                favicon,
+                ..CapturedResponse::new(status, headers, lines.collect())
+            },
+        })
+    }
+
+    /// The first `# key: value` line with the key
+    pub fn meta(&self, key: &str) -> Option<&str> {
+        self.meta
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn blank_cert() -> CertInfo {
+    CertInfo {
+        subject: String::new(),
+        issuer: String::new(),
+        sans: vec![],
+        not_before: Utc::now(),
+        not_after: Utc::now(),
+        sha256: String::new(),
+    }
+}
+
+/// Every `.txt` file in the directory, by name
+pub fn load_dir(dir: &Path) -> Result<Vec<SavedResponse>> {
+    let mut paths = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .map(|e| Ok(e?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.retain(|p| p.extension().is_some_and(|e| e == "txt"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            let content = std::fs::read_to_string(p)
+                .with_context(|| format!("failed to read {}", p.display()))?;
+
+            SavedResponse::parse(&name, &content).with_context(|| format!("invalid file {name}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_saved_responses() {
+        let saved = SavedResponse::parse(
+            "saved.txt",
+            "# ip: 10.208.2.22\n# url: https://10.208.2.22:443/\n# expect: unidentified\n\
+             # cert-subject: CN=NPI8D0C2B\n# cert-san: npi8d0c2b.local\n# favicon-mmh3: -156908512\n\
+             HTTP 401\nServer: Virata-EmWeb/R6_2_1\n\n<html>\n<title>Login</title>\n",
+        )
+        .unwrap();
+
+        assert_eq!(saved.ip.to_string(), "10.208.2.22");
+        assert_eq!(saved.meta("expect"), Some("unidentified"));
+        assert_eq!(saved.response.status, 401);
+        assert_eq!(
+            saved.response.headers("server").next(),
+            Some("Virata-EmWeb/R6_2_1")
+        );
+        assert_eq!(saved.response.title.as_deref(), Some("Login"));
+        assert_eq!(saved.response.body, "<html>\n<title>Login</title>\n");
+
+        let cert = saved.response.cert.unwrap();
+        assert_eq!(cert.subject, "CN=NPI8D0C2B");
+        assert_eq!(cert.sans, ["npi8d0c2b.local"]);
+        assert_eq!(saved.response.favicon.unwrap().mmh3, -156908512);
+    }
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{Display, Formatter},
+    net::IpAddr,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// The parts of a peer certificate worth keeping, self-signed ones often carry the model, serial or hostname
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CertInfo {
+    /// e.x. `CN=idrac-7xk2lm3, OU=Remote Access Group, O=Dell Inc.`
+    pub subject: String,
+    pub issuer: String,
+    /// dns names and ips the certificate is valid for
+    pub sans: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// hex sha256 of the whole certificate, the same fingerprint browsers show
+    pub sha256: String,
+}
+
+impl CertInfo {
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let validity = cert.validity();
+
+        let sans = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|n| match n {
+                    GeneralName::DNSName(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => Some(IpAddr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                        16 => Some(IpAddr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        Some(Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+            not_before: DateTime::from_timestamp(validity.not_before.timestamp(), 0)?,
+            not_after: DateTime::from_timestamp(validity.not_after.timestamp(), 0)?,
+            sha256: format!("{:x}", Sha256::digest(der)),
+        })
+    }
+
+    /// The `CN=` of the subject, e.x. `idrac-7xk2lm3`
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .split(", ")
+            .find_map(|part| part.strip_prefix("CN="))
+    }
+
+    /// The first dns name the certificate is for, ips aren't names
+    pub fn hostname(&self) -> Option<String> {
+        self.sans
+            .iter()
+            .find(|s| s.parse::<IpAddr>().is_err() && !s.starts_with('*'))
+            .cloned()
+    }
+
+    pub fn self_signed(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    pub fn expired(&self) -> bool {
+        self.not_after < Utc::now()
+    }
+}
+
+impl Display for CertInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("subject {}", self.subject))?;
+        if self.self_signed() {
+            f.write_str(" (self-signed)")?;
+        } else {
+            f.write_fmt(format_args!(", issuer {}", self.issuer))?;
+        }
+
+        if !self.sans.is_empty() {
+            f.write_fmt(format_args!(", sans {}", self.sans.join(", ")))?;
+        }
+
+        f.write_fmt(format_args!(
+            ", valid {} to {}{}, sha256 {}",
+            self.not_before.date_naive(),
+            self.not_after.date_naive(),
+            if self.expired() { " (expired)" } else { "" },
+            self.sha256
+        ))
+    }
+}
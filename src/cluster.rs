@@ -0,0 +1,284 @@
+use crate::{
+    archive, capture, favicon::Favicon, id::fingerprint::FingerprintDb, response::CapturedResponse,
+    FingerprintSuggestArgs,
+};
+use anyhow::Result;
+use regex::Regex;
+use std::{collections::HashSet, net::IpAddr, path::Path, sync::OnceLock};
+
+/// A response prepared for comparing, the host's own ip is replaced with `{ip}` like fingerprints expect
+struct Sample {
+    ip: IpAddr,
+    identified: bool,
+    title: Option<String>,
+    /// the title as served, what `title_regex` rules are matched against
+    raw_title: Option<String>,
+    server: Option<String>,
+    favicon: Option<Favicon>,
+    body: String,
+    words: HashSet<String>,
+}
+
+impl Sample {
+    fn new(ip: IpAddr, res: &CapturedResponse, identified: bool) -> Self {
+        let template = |s: &str| s.replace(&ip.to_string(), "{ip}");
+        let body = template(&res.body);
+        let words = body
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.len() >= 3)
+            .map(str::to_owned)
+            .collect();
+
+        Self {
+            ip,
+            identified,
+            title: res.title.as_deref().map(template),
+            raw_title: res.title.clone(),
+            server: res.headers("server").next().map(str::to_owned),
+            favicon: res.favicon.clone(),
+            body,
+            words,
+        }
+    }
+
+    fn similar(&self, other: &Self, similarity: f32) -> bool {
+        if self.favicon.is_some() && self.favicon == other.favicon {
+            return true;
+        }
+        if self.title.is_some() && self.title == other.title && self.server == other.server {
+            return true;
+        }
+
+        // empty bodies, e.x. bare 401s, only have their server header to go by
+        if self.words.is_empty() || other.words.is_empty() {
+            return self.words.is_empty()
+                && other.words.is_empty()
+                && self.server.is_some()
+                && self.server == other.server;
+        }
+
+        let shared = self.words.intersection(&other.words).count();
+        let all = self.words.union(&other.words).count();
+        shared as f32 / all as f32 >= similarity
+    }
+}
+
+/// Groups responses no fingerprint matched and proposes a rule for each group
+pub async fn suggest_fingerprints(args: FingerprintSuggestArgs) -> Result<()> {
+    let fingerprints = FingerprintDb::with_file(args.fingerprints.as_deref())?;
+
+    // a directory from scan --save-unidentified, otherwise a recording
+    let responses = if Path::new(&args.source).is_dir() {
+        capture::load_dir(Path::new(&args.source))?
+            .into_iter()
+            .map(|s| (s.ip, s.response))
+            .collect::<Vec<_>>()
+    } else {
+        archive::read(&args.source)
+            .await?
+            .into_values()
+            .filter_map(|h| h.primary())
+            .map(|(t, res)| (t.ip, res))
+            .collect()
+    };
+
+    let samples = responses
+        .iter()
+        .map(|(ip, res)| {
+            let identified = fingerprints.identify(ip, res).best().is_some();
+            Sample::new(*ip, res, identified)
+        })
+        .collect::<Vec<_>>();
+
+    let mut clusters: Vec<Vec<&Sample>> = vec![];
+    for s in samples.iter().filter(|s| !s.identified) {
+        match clusters
+            .iter_mut()
+            .find(|c| c[0].similar(s, args.similarity))
+        {
+            Some(c) => c.push(s),
+            None => clusters.push(vec![s]),
+        }
+    }
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.len()));
+
+    let unidentified = clusters.iter().map(Vec::len).sum::<usize>();
+    let (shown, small): (Vec<_>, Vec<_>) = clusters
+        .iter()
+        .partition(|c| c.len() >= args.min_size.max(1));
+    println!(
+        "-- {unidentified} of {} responses unidentified, {} clusters of {} or more --",
+        samples.len(),
+        shown.len(),
+        args.min_size.max(1)
+    );
+
+    for cluster in shown {
+        let first = cluster[0];
+        let examples = cluster
+            .iter()
+            .take(5)
+            .map(|s| s.ip.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let title = first
+            .title
+            .as_ref()
+            .map_or_else(|| String::from("no title"), |t| format!("title {t:?}"));
+        let server = first
+            .server
+            .as_ref()
+            .map_or_else(|| String::from("no server"), |s| format!("server {s:?}"));
+        let favicon = first
+            .favicon
+            .as_ref()
+            .map(|f| format!(", favicon {}", f.mmh3))
+            .unwrap_or_default();
+        println!("{} hosts: {title}, {server}{favicon}", cluster.len());
+        println!("  e.x. {examples}");
+
+        let others = samples
+            .iter()
+            .filter(|s| !cluster.iter().any(|c| std::ptr::eq(*c, *s)))
+            .collect::<Vec<_>>();
+        match propose(cluster, &others) {
+            Some(rule) => println!("  rules = [{rule}]"),
+            None => println!("  nothing is unique to this cluster"),
+        }
+    }
+
+    let alone = small.iter().map(|c| c.len()).sum::<usize>();
+    if alone > 0 {
+        println!("{alone} responses are in smaller clusters");
+    }
+
+    Ok(())
+}
+
+/// A rule every member matches and no other response does, in the fingerprint file's syntax
+fn propose(cluster: &[&Sample], others: &[&Sample]) -> Option<String> {
+    let quote = |s: &str| toml::Value::String(s.to_owned()).to_string();
+
+    let titles = cluster
+        .iter()
+        .map(|s| s.title.as_deref())
+        .collect::<Option<Vec<&str>>>();
+    if let Some(titles) = &titles {
+        let title = titles[0];
+        let unique = !others
+            .iter()
+            .any(|o| o.title.as_deref().is_some_and(|t| t.contains(title)));
+        if !title.is_empty() && titles.iter().all(|t| *t == title) && unique {
+            return Some(format!("{{ title = {} }}", quote(title)));
+        }
+
+        // titles that only differ in numbers, e.x. a name with the room number in it
+        static DIGITS: OnceLock<Regex> = OnceLock::new();
+        let digits = DIGITS.get_or_init(|| Regex::new(r"\d+").unwrap());
+        let pattern = |t: &str| {
+            let escaped = regex::escape(t);
+            let generalized = digits.replace_all(&escaped, r"\d+");
+            format!(
+                "^{}$",
+                generalized.replace(r"\{ip\}", r"\d+\.\d+\.\d+\.\d+")
+            )
+        };
+        let generalized = pattern(title);
+        if titles.iter().all(|t| pattern(t) == generalized) {
+            let r = Regex::new(&generalized).ok()?;
+            let unique = !others
+                .iter()
+                .any(|o| o.raw_title.as_deref().is_some_and(|t| r.is_match(t)));
+            if unique {
+                return Some(format!("{{ title_regex = {} }}", quote(&generalized)));
+            }
+        }
+    }
+
+    let favicon = cluster[0].favicon.as_ref();
+    if let Some(favicon) = favicon.filter(|f| {
+        cluster.iter().all(|s| s.favicon.as_ref() == Some(*f))
+            && !others.iter().any(|o| o.favicon.as_ref() == Some(*f))
+    }) {
+        return Some(format!("{{ favicon = {{ mmh3 = {} }} }}", favicon.mmh3));
+    }
+
+    // lines of minified pages can be the whole page, those are split between tags
+    let mut candidates = cluster[0]
+        .body
+        .lines()
+        .flat_map(|l| {
+            if l.len() > 120 {
+                l.split_inclusive('>').collect::<Vec<&str>>()
+            } else {
+                vec![l]
+            }
+        })
+        .map(str::trim)
+        .filter(|l| (10..=120).contains(&l.len()) && l.chars().any(char::is_alphabetic))
+        .collect::<Vec<&str>>();
+    // fingerprints score up to 40 characters, past that shorter is easier to read
+    candidates.sort_by_key(|l| (std::cmp::Reverse(l.len().min(40)), l.len()));
+    if let Some(line) = candidates.iter().find(|l| {
+        cluster.iter().all(|s| s.body.contains(**l)) && !others.iter().any(|o| o.body.contains(**l))
+    }) {
+        return Some(format!("{{ body = {} }}", quote(line)));
+    }
+
+    let server = cluster[0].server.as_deref()?;
+    let unique = !others
+        .iter()
+        .any(|o| o.server.as_deref().is_some_and(|s| s.contains(server)));
+    (cluster.iter().all(|s| s.server.as_deref() == Some(server)) && unique).then(|| {
+        format!(
+            "{{ header = {{ name = \"server\", contains = {} }} }}",
+            quote(server)
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ip: &str, title: &str) -> Sample {
+        let body = format!("<html><head><title>{title}</title></head></html>");
+        let ip = ip.parse().unwrap();
+        Sample::new(ip, &CapturedResponse::new(200, vec![], body), false)
+    }
+
+    #[test]
+    fn proposes_title_rules() {
+        let a = sample("10.208.3.1", "Library Kiosk");
+        let b = sample("10.208.3.2", "Library Kiosk");
+        let other = sample("10.208.3.3", "Library Kiosk Admin");
+        assert_eq!(
+            propose(&[&a, &b], &[]).unwrap(),
+            r#"{ title = "Library Kiosk" }"#
+        );
+        // the other title contains the cluster's, so it has to be anchored
+        assert_eq!(
+            propose(&[&a, &b], &[&other]).unwrap(),
+            r#"{ title_regex = "^Library Kiosk$" }"#
+        );
+
+        let a = sample("10.208.3.1", "Room 101 10.208.3.1");
+        let b = sample("10.208.3.2", "Room 102 10.208.3.2");
+        assert_eq!(
+            propose(&[&a, &b], &[]).unwrap(),
+            r"{ title_regex = '^Room \d+ \d+\.\d+\.\d+\.\d+$' }"
+        );
+    }
+
+    #[test]
+    fn title_regex_is_checked_against_served_titles() {
+        let a = sample("10.208.3.1", "Room 101 10.208.3.1");
+        let b = sample("10.208.3.2", "Room 102 10.208.3.2");
+        // templated this is `Room 7 {ip}`, which the regex doesn't match, but the served title does
+        let other = sample("10.208.3.3", "Room 7 10.208.3.3");
+
+        let rule = propose(&[&a, &b], &[&other]);
+        assert!(rule.is_none_or(|r| !r.starts_with("{ title_regex")));
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{cert::CertInfo, id::devices::NetworkDevice, scanner::ScanResult};
+use std::{cmp::Reverse, collections::HashSet, net::IpAddr};
+
+/// What identifies a device no matter which ip or port it answered on
+fn identifiers(r: &ScanResult) -> HashSet<String> {
+    let mut ids = HashSet::new();
+
+    // DNS-SD's `UUID` is the same as IPP's `printer-uuid`
+    let uuids = [
+        r.ipp.as_ref().and_then(|i| i.uuid.as_deref()),
+        r.mdns.as_ref().and_then(|m| m.uuid.as_deref()),
+    ];
+    for uuid in uuids.into_iter().flatten() {
+        ids.insert(format!("uuid:{}", uuid.to_lowercase()));
+    }
+
+    if let Some(serial) = r.snmp.as_ref().and_then(|s| s.serial.as_deref()) {
+        let serial = serial.trim();
+        if !serial.is_empty() {
+            ids.insert(format!("serial:{serial}"));
+        }
+    }
+
+    if let Some(mac) = &r.mac {
+        ids.insert(format!("mac:{mac}"));
+    }
+
+    if let Some(cert) = r.cert.as_ref().filter(|c| host_specific(c)) {
+        ids.insert(format!("cert:{}", cert.sha256));
+    }
+
+    ids
+}
+
+/// Only a certificate the device made for itself identifies it, a wildcard or CA issued one is often shared by
+/// every device of a site. Product lines also ship one default certificate, so it has to name something only
+/// this device has
+fn host_specific(cert: &CertInfo) -> bool {
+    let names = cert
+        .common_name()
+        .into_iter()
+        .chain(cert.sans.iter().map(String::as_str))
+        .collect::<Vec<&str>>();
+    let wildcard = names.iter().any(|n| n.starts_with('*'));
+
+    cert.self_signed() && !wildcard && names.iter().any(|n| unique_name(n))
+}
+
+/// A serial, mac or numbered hostname, e.x. `NPI3F2A1B` or `lib-2f-laser`. `localhost`, ips and product names
+/// like `HP Jetdirect` are the same on every device
+fn unique_name(name: &str) -> bool {
+    let host = name.split('.').next().unwrap_or_default();
+
+    !name.contains(char::is_whitespace)
+        && name.parse::<IpAddr>().is_err()
+        && !host.eq_ignore_ascii_case("localhost")
+        && host.chars().any(|c| c.is_ascii_digit())
+        && host.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Merges results that are the same device on several ips into one, the other ips end up in its `aliases`
+pub fn merge(results: Vec<ScanResult>) -> Vec<ScanResult> {
+    let found = results.len();
+    // every group with the identifiers of all its members, so a chain of shared ids ends up as one group
+    let mut groups: Vec<(HashSet<String>, Vec<ScanResult>)> = vec![];
+
+    for r in results {
+        let ids = identifiers(&r);
+        let (mut matching, rest): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .partition(|(group_ids, _)| !group_ids.is_disjoint(&ids));
+        groups = rest;
+
+        let mut group = matching.pop().unwrap_or_default();
+        for (other_ids, members) in matching {
+            group.0.extend(other_ids);
+            group.1.extend(members);
+        }
+        group.0.extend(ids);
+        group.1.push(r);
+        groups.push(group);
+    }
+
+    let mut merged = groups
+        .into_iter()
+        .filter_map(|(_, members)| combine(members))
+        .collect::<Vec<ScanResult>>();
+    merged.sort_by_key(|r| r.ip);
+
+    if merged.len() < found {
+        println!("Merged {found} results into {} devices", merged.len());
+        for r in merged.iter().filter(|r| !r.aliases.is_empty()) {
+            let aliases = r
+                .aliases
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<String>>()
+                .join(", ");
+            println!("{}: {} is also on {aliases}", r.name(), r.device);
+        }
+    }
+
+    merged
+}
+
+/// The most confidently identified member, with the gaps filled in by the others
+fn combine(mut members: Vec<ScanResult>) -> Option<ScanResult> {
+    members.sort_by_key(|r| {
+        let confidence = match &r.device {
+            NetworkDevice::Identified(d) => (d.confidence * 1000.0) as u32 + 1,
+            NetworkDevice::Unidentified => 0,
+        };
+        (Reverse(confidence), r.ip)
+    });
+
+    let mut members = members.into_iter();
+    let mut primary = members.next()?;
+
+    for r in members {
+        primary.aliases.push(r.ip);
+        primary.aliases.extend(r.aliases);
+
+        for target in r.open {
+            if !primary.open.contains(&target) {
+                primary.open.push(target);
+            }
+        }
+
+        primary.seen_at = primary.seen_at.min(r.seen_at);
+        primary.cert = primary.cert.or(r.cert);
+        primary.favicon = primary.favicon.or(r.favicon);
+        primary.hostname = primary.hostname.or(r.hostname);
+        primary.web_hostname = primary.web_hostname.or(r.web_hostname);
+        primary.mac = primary.mac.or(r.mac);
+        primary.oui_vendor = primary.oui_vendor.or(r.oui_vendor);
+        primary.vendor_mismatch |= r.vendor_mismatch;
+        primary.ipp = primary.ipp.or(r.ipp);
+        primary.snmp = primary.snmp.or(r.snmp);
+        primary.pjl = primary.pjl.or(r.pjl);
+        primary.mdns = primary.mdns.or(r.mdns);
+        primary.wsd = primary.wsd.or(r.wsd);
+    }
+
+    primary.aliases.sort();
+    primary.aliases.dedup();
+    Some(primary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::{Scheme, Target};
+    use chrono::{TimeZone, Utc};
+
+    fn result(ip: &str, cert: CertInfo) -> ScanResult {
+        let ip = ip.parse().unwrap();
+        let target = Target {
+            ip,
+            port: 443,
+            scheme: Scheme::Https,
+        };
+
+        ScanResult {
+            cert: Some(cert),
+            ..ScanResult::new(ip, target, NetworkDevice::Unidentified)
+        }
+    }
+
+    fn cert(subject: &str, issuer: &str, sans: &[&str]) -> CertInfo {
+        CertInfo {
+            subject: subject.to_owned(),
+            issuer: issuer.to_owned(),
+            sans: sans.iter().map(|s| (*s).to_owned()).collect(),
+            not_before: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            not_after: Utc.with_ymd_and_hms(2034, 1, 1, 0, 0, 0).unwrap(),
+            sha256: String::from("9f86d081884c7d65"),
+        }
+    }
+
+    #[test]
+    fn shared_wildcard_certificates_dont_merge() {
+        let wildcard = cert(
+            "CN=*.school.local",
+            "CN=School Issuing CA, DC=school, DC=local",
+            &["*.school.local"],
+        );
+
+        let merged = merge(vec![
+            result("10.208.2.22", wildcard.clone()),
+            result("10.208.2.23", wildcard),
+        ]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    #[test]
+    fn self_signed_device_certificates_merge() {
+        let own = cert("CN=NPI3F2A1B", "CN=NPI3F2A1B", &["npi3f2a1b.local"]);
+
+        let merged = merge(vec![
+            result("10.208.2.22", own.clone()),
+            result("10.208.9.4", own),
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].aliases, ["10.208.9.4".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn default_certificates_dont_merge() {
+        let default = cert("CN=localhost", "CN=localhost", &["localhost"]);
+
+        let merged = merge(vec![
+            result("10.208.2.22", default.clone()),
+            result("10.208.2.23", default),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert!(merged.iter().all(|r| r.aliases.is_empty()));
+    }
+
+    #[test]
+    fn only_device_certificates_identify() {
+        assert!(host_specific(&cert(
+            "CN=idrac-7xk2lm3",
+            "CN=idrac-7xk2lm3",
+            &[]
+        )));
+        // issued by a CA, the same one could be deployed to every printer
+        assert!(!host_specific(&cert(
+            "CN=printer.school.local",
+            "CN=School Issuing CA",
+            &["printer.school.local"]
+        )));
+        assert!(!host_specific(&cert("CN=*.local", "CN=*.local", &[])));
+        assert!(!host_specific(&cert("O=HP", "O=HP", &[])));
+        // shipped the same on every device of a product line
+        for name in [
+            "HP Jetdirect",
+            "localhost.localdomain",
+            "192.168.0.1",
+            "printer",
+        ] {
+            let subject = format!("CN={name}, O=HP");
+            assert!(!host_specific(&cert(&subject, &subject, &[])), "{name}");
+        }
+        assert!(host_specific(&cert(
+            "CN=HP Jetdirect",
+            "CN=HP Jetdirect",
+            &["lib-2f-laser.school.local"]
+        )));
+    }
+}
@@ -0,0 +1,423 @@
+use crate::{
+    id::devices::NetworkDevice,
+    output::{self, OutputFormat},
+    scanner::ScanResult,
+    DiffArgs,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    net::IpAddr,
+};
+
+/// What a diff compares, every output format (even text) can be read back into this
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub ip: IpAddr,
+    /// hostname or other readable name
+    pub name: Option<String>,
+    /// the device as displayed, e.x. `HP LaserJet M402dn (Printer)`
+    pub device: String,
+    /// fingerprint id, vendor and model of an identified device, text output doesn't keep them
+    pub id: Option<(String, String, String)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub new: Vec<Entry>,
+    pub gone: Vec<Entry>,
+    /// same ip, identified as something else
+    pub changed: Vec<(Entry, Entry)>,
+    /// same device, found on another ip
+    pub moved: Vec<(Entry, Entry)>,
+}
+
+impl From<&ScanResult> for Entry {
+    fn from(r: &ScanResult) -> Self {
+        Self {
+            ip: r.ip,
+            name: Some(r.name()).filter(|n| *n != r.ip.to_string()),
+            device: r.device.to_string(),
+            id: match &r.device {
+                NetworkDevice::Identified(d) => {
+                    Some((d.fingerprint.clone(), d.vendor.clone(), d.model.clone()))
+                }
+                NetworkDevice::Unidentified => None,
+            },
+        }
+    }
+}
+
+impl Entry {
+    /// `name (ip)`, or only the ip without a name
+    fn address(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{name} ({})", self.ip),
+            None => self.ip.to_string(),
+        }
+    }
+
+    /// By fingerprint and model when both sides have them, so rewording how a device is displayed
+    /// isn't a change, otherwise by the displayed device
+    fn same_device(&self, other: &Entry) -> bool {
+        match (&self.id, &other.id) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.device == other.device,
+        }
+    }
+}
+
+pub fn load(path: &str) -> Result<Vec<Entry>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+
+    parse(&content).with_context(|| format!("failed to parse {path}"))
+}
+
+/// Reads entries from any output format
+pub fn parse(content: &str) -> Result<Vec<Entry>> {
+    match OutputFormat::detect(content) {
+        OutputFormat::Text => content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(parse_text_line)
+            .collect(),
+        format => Ok(output::parse_results(content, format)?
+            .iter()
+            .map(Entry::from)
+            .collect()),
+    }
+}
+
+/// `ip:device` or `[ipv6]:device`, device names can have colons of their own
+fn parse_text_line(l: &str) -> Result<Entry> {
+    let (ip, device) = match l.strip_prefix('[') {
+        Some(l) => l.split_once("]:").context("expected [ip]:device")?,
+        None => l.split_once(':').context("expected ip:device")?,
+    };
+    let (device, name) = match device.rsplit_once(" | ") {
+        Some((device, name)) => (device, Some(name.to_owned())),
+        None => (device, None),
+    };
+
+    Ok(Entry {
+        ip: ip.parse().with_context(|| format!("invalid ip {ip}"))?,
+        name,
+        device: strip_aliases(device).to_owned(),
+        id: None,
+    })
+}
+
+/// Merged devices list their other ips after the device, e.x. `HP LaserJet M402dne (Printer) (also on 10.208.9.4)`
+fn strip_aliases(device: &str) -> &str {
+    device
+        .strip_suffix(')')
+        .and_then(|d| d.rsplit_once(" (also on "))
+        .filter(|(_, aliases)| aliases.split(", ").all(|a| a.parse::<IpAddr>().is_ok()))
+        .map_or(device, |(device, _)| device)
+}
+
+pub fn diff(old: &[Entry], new: &[Entry]) -> Diff {
+    let old_by_ip = old.iter().map(|e| (e.ip, e)).collect::<HashMap<_, _>>();
+    let new_by_ip = new.iter().map(|e| (e.ip, e)).collect::<HashMap<_, _>>();
+
+    let mut d = Diff::default();
+    let mut gone = old
+        .iter()
+        .filter(|e| !new_by_ip.contains_key(&e.ip))
+        .cloned()
+        .collect::<Vec<Entry>>();
+    let mut appeared = vec![];
+
+    for e in new {
+        match old_by_ip.get(&e.ip) {
+            Some(o) if !o.same_device(e) => d.changed.push(((*o).clone(), e.clone())),
+            Some(_) => {}
+            None => appeared.push(e.clone()),
+        }
+    }
+
+    // a device that vanished from one ip and appeared on another is only a move when it's unambiguous
+    let unique = |list: &[Entry], e: &Entry| list.iter().filter(|o| o.same_device(e)).count() == 1;
+    let unidentified = NetworkDevice::Unidentified.to_string();
+
+    for e in appeared {
+        let pair = gone
+            .iter()
+            .position(|g| g.same_device(&e))
+            .filter(|_| e.device != unidentified && unique(&gone, &e) && unique(new, &e));
+
+        match pair {
+            Some(i) => d.moved.push((gone.remove(i), e)),
+            None => d.new.push(e),
+        }
+    }
+
+    d.gone = gone;
+    d
+}
+
+pub fn diff_files(args: &DiffArgs) -> Result<()> {
+    let d = diff(&load(&args.old)?, &load(&args.new)?);
+    print!("{d}");
+
+    Ok(())
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for e in &self.new {
+            f.write_fmt(format_args!("+ {} {}\n", e.address(), e.device))?;
+        }
+
+        for e in &self.gone {
+            f.write_fmt(format_args!("- {} {}\n", e.address(), e.device))?;
+        }
+
+        for (o, n) in &self.changed {
+            f.write_fmt(format_args!(
+                "~ {} {} -> {}\n",
+                n.address(),
+                o.device,
+                n.device
+            ))?;
+        }
+
+        for (o, n) in &self.moved {
+            f.write_fmt(format_args!(
+                "> {} moved {} -> {}\n",
+                n.device,
+                o.address(),
+                n.address()
+            ))?;
+        }
+
+        f.write_fmt(format_args!(
+            "{} new, {} gone, {} changed, {} moved\n",
+            self.new.len(),
+            self.gone.len(),
+            self.changed.len(),
+            self.moved.len()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        id::devices::{Category, Device},
+        output::RecordEncoder,
+        target::{Scheme, Target},
+    };
+
+    fn result(ip: &str, device: Option<(&str, &str)>) -> ScanResult {
+        let ip = ip.parse().unwrap();
+        let device = match device {
+            Some((fingerprint, model)) => NetworkDevice::Identified(Device {
+                fingerprint: fingerprint.to_owned(),
+                vendor: String::from("HP"),
+                model: model.to_owned(),
+                category: Category::Printer,
+                confidence: 0.5,
+                printer: None,
+            }),
+            None => NetworkDevice::Unidentified,
+        };
+        let target = Target {
+            ip,
+            port: 80,
+            scheme: Scheme::Http,
+        };
+
+        ScanResult::new(ip, target, device)
+    }
+
+    fn entries(results: &[ScanResult]) -> Vec<Entry> {
+        results.iter().map(Entry::from).collect()
+    }
+
+    fn ips(list: &[Entry]) -> Vec<String> {
+        list.iter().map(|e| e.ip.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_new_gone_changed_and_moved() {
+        let m402 = Some(("hp-m402", "LaserJet M402dne"));
+        let m506 = Some(("hp-m506", "LaserJet M506"));
+        let old = entries(&[
+            result("10.0.0.1", m402),
+            result("10.0.0.2", m506),
+            result("10.0.0.3", None),
+            result("10.0.0.4", Some(("hp-m608", "LaserJet M608"))),
+        ]);
+        let new = entries(&[
+            result("10.0.0.1", m506),
+            result("10.0.0.5", Some(("hp-m608", "LaserJet M608"))),
+            result("10.0.0.6", None),
+            result("10.0.0.7", Some(("hp-m1", "LaserJet M1"))),
+        ]);
+
+        let d = diff(&old, &new);
+        assert_eq!(ips(&d.new), ["10.0.0.6", "10.0.0.7"]);
+        assert_eq!(ips(&d.gone), ["10.0.0.2", "10.0.0.3"]);
+        assert_eq!(d.changed.len(), 1);
+        assert_eq!(
+            (
+                d.changed[0].0.ip.to_string(),
+                d.changed[0].1.device.as_str()
+            ),
+            (String::from("10.0.0.1"), "HP LaserJet M506 (Printer)")
+        );
+        assert_eq!(d.moved.len(), 1);
+        assert_eq!(
+            (d.moved[0].0.ip.to_string(), d.moved[0].1.ip.to_string()),
+            (String::from("10.0.0.4"), String::from("10.0.0.5"))
+        );
+    }
+
+    #[test]
+    fn only_unambiguous_devices_move() {
+        let m402 = Some(("hp-m402", "LaserJet M402dne"));
+        let old = entries(&[result("10.0.0.1", m402), result("10.0.0.2", m402)]);
+        let new = entries(&[result("10.0.0.3", m402), result("10.0.0.4", m402)]);
+
+        let d = diff(&old, &new);
+        assert!(d.moved.is_empty());
+        assert_eq!((d.new.len(), d.gone.len()), (2, 2));
+    }
+
+    #[test]
+    fn compares_fingerprints_not_display() {
+        let old = entries(&[result("10.0.0.1", Some(("hp-m402", "LaserJet M402dne")))]);
+        let mut new = entries(&[result("10.0.0.1", Some(("hp-m402", "LaserJet M402dne")))]);
+        new[0].device = String::from("HP LaserJet M402dne (Office Printer)");
+        assert!(diff(&old, &new).changed.is_empty());
+
+        let new = entries(&[result(
+            "10.0.0.1",
+            Some(("hp-m402", "LaserJet Pro M402dne")),
+        )]);
+        assert_eq!(diff(&old, &new).changed.len(), 1);
+
+        // text output only has the display to go on
+        let text = parse("10.0.0.1:HP LaserJet M402dne (Printer)\n").unwrap();
+        assert!(diff(&text, &old).changed.is_empty());
+    }
+
+    #[test]
+    fn loads_every_format() {
+        let mut merged = result("10.0.0.1", Some(("hp-m402", "LaserJet M402dne")));
+        merged.aliases = vec!["10.0.0.9".parse().unwrap()];
+        merged.hostname = Some(String::from("lib-2f-laser.school.local"));
+        let results = vec![
+            merged,
+            result(
+                "fe80::3e52:82ff:fe3f:2a1b",
+                Some(("hp-m506", "LaserJet M506")),
+            ),
+            result("10.0.0.3", None),
+        ];
+        let expected = [
+            (
+                "10.0.0.1",
+                "HP LaserJet M402dne (Printer)",
+                Some("lib-2f-laser.school.local"),
+            ),
+            (
+                "fe80::3e52:82ff:fe3f:2a1b",
+                "HP LaserJet M506 (Printer)",
+                None,
+            ),
+            ("10.0.0.3", "Unidentified", None),
+        ];
+
+        for format in [
+            OutputFormat::Text,
+            OutputFormat::Json,
+            OutputFormat::Ndjson,
+            OutputFormat::Csv,
+        ] {
+            let mut encoder = RecordEncoder::new(format);
+            let mut content = String::from(encoder.header());
+            for r in &results {
+                content.push_str(&encoder.record(r).unwrap());
+            }
+            content.push_str(encoder.footer());
+
+            let loaded = parse(&content).unwrap();
+            let loaded = loaded
+                .iter()
+                .map(|e| (e.ip.to_string(), e.device.as_str(), e.name.as_deref()))
+                .collect::<Vec<_>>();
+            let expected = expected
+                .iter()
+                .map(|(ip, device, name)| ((*ip).to_owned(), *device, *name))
+                .collect::<Vec<_>>();
+            assert_eq!(loaded, expected, "{format:?}");
+            assert!(diff(&parse(&content).unwrap(), &entries(&results))
+                .changed
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn loads_text_starting_with_ipv6() {
+        let results = [
+            result(
+                "fe80::3e52:82ff:fe3f:2a1b",
+                Some(("hp-m506", "LaserJet M506")),
+            ),
+            result("10.0.0.3", None),
+        ];
+
+        let mut encoder = RecordEncoder::new(OutputFormat::Text);
+        let content = results
+            .iter()
+            .map(|r| encoder.record(r).unwrap())
+            .collect::<String>();
+        assert!(content.starts_with('['));
+        assert_eq!(OutputFormat::detect(&content), OutputFormat::Text);
+
+        let loaded = parse(&content).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].ip.to_string(), "fe80::3e52:82ff:fe3f:2a1b");
+        assert_eq!(loaded[0].device, "HP LaserJet M506 (Printer)");
+
+        // empty json arrays, with or without whitespace, are still json
+        assert_eq!(OutputFormat::detect("[]"), OutputFormat::Json);
+        assert_eq!(OutputFormat::detect("[\n]\n"), OutputFormat::Json);
+        assert_eq!(
+            OutputFormat::detect("[\n  {\"ip\": 1}]"),
+            OutputFormat::Json
+        );
+    }
+
+    #[test]
+    fn strips_only_lists_of_ips() {
+        assert_eq!(
+            strip_aliases("HP LaserJet M402dne (Printer) (also on 10.0.0.9, fe80::1)"),
+            "HP LaserJet M402dne (Printer)"
+        );
+        assert_eq!(
+            strip_aliases("Acme Box (also on sale) (Other)"),
+            "Acme Box (also on sale) (Other)"
+        );
+        assert_eq!(
+            strip_aliases("Acme Box (Other) (also on the roof)"),
+            "Acme Box (Other) (also on the roof)"
+        );
+
+        let e = parse("10.0.0.1:HP LaserJet M402dne (Printer) (also on 10.0.0.9) | lib-2f-laser\n")
+            .unwrap();
+        assert_eq!(e[0].device, "HP LaserJet M402dne (Printer)");
+        assert_eq!(e[0].name.as_deref(), Some("lib-2f-laser"));
+    }
+
+    #[test]
+    fn rejects_lines_without_an_ip() {
+        assert!(parse("HP LaserJet M402dne (Printer)\n").is_err());
+        assert!(parse("[fe80::1:HP LaserJet\n").is_err());
+    }
+}
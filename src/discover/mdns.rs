@@ -0,0 +1,317 @@
+use crate::{
+    id::devices::NetworkDevice,
+    scanner::ScanResult,
+    target::{Scheme, Target},
+};
+use anyhow::Result;
+use mdns_sd::{ResolvedService, ScopedIp, ServiceDaemon, ServiceEvent};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, net::IpAddr};
+use tokio::{
+    sync::mpsc,
+    time::{timeout_at, Instant},
+};
+
+/// Printer service types in order of preference for the result's target, with what they're reached over
+const SERVICES: &[(&str, Scheme)] = &[
+    ("_ipp._tcp.local.", Scheme::Http),
+    ("_ipps._tcp.local.", Scheme::Https),
+    ("_pdl-datastream._tcp.local.", Scheme::Raw),
+    ("_printer._tcp.local.", Scheme::Lpd),
+];
+
+/// What a printer advertised over DNS-SD, the TXT keys are from the Bonjour printing spec
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DnsSdInfo {
+    /// e.x. `HP LaserJet Pro M402dne (3F2A1B)`
+    pub instance: String,
+    pub hostname: String,
+    /// every service type advertised, e.x. `_ipp._tcp`
+    pub services: Vec<String>,
+    /// `ty`, the make and model
+    pub ty: Option<String>,
+    /// `pdl`, the accepted document formats
+    pub pdl: Vec<String>,
+    pub color: Option<bool>,
+    pub duplex: Option<bool>,
+    /// `UUID` of the printer, matches IPP's `printer-uuid`
+    pub uuid: Option<String>,
+    /// `rp`, the queue's resource path, e.x. `ipp/print`
+    pub rp: Option<String>,
+}
+
+/// Browses every printer service type until the deadline, resolved services are collected as they come in
+pub async fn browse(deadline: Instant) -> Result<Vec<ResolvedService>> {
+    let daemon = ServiceDaemon::new()?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    for (service, _) in SERVICES {
+        let events = daemon.browse(service)?;
+        let sender = sender.clone();
+
+        tokio::spawn(async move {
+            while let Ok(event) = events.recv_async().await {
+                if let ServiceEvent::ServiceResolved(s) = event {
+                    if sender.send(s).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut services = vec![];
+    while let Ok(Some(s)) = timeout_at(deadline, receiver.recv()).await {
+        println!("Found {} on {}", s.fullname, s.host);
+        services.push(*s);
+    }
+
+    let _ = daemon.shutdown();
+    Ok(services)
+}
+
+/// One result per address, every service a printer advertised on it is folded into one record
+pub fn results(services: &[ResolvedService]) -> Vec<ScanResult> {
+    let mut hosts = BTreeMap::<IpAddr, Vec<&ResolvedService>>::new();
+    for s in services {
+        // a printer usually answers on v4 and a link local v6, v4 sorts first and is what every other source knows it by
+        let ip = s.get_addresses().iter().map(ScopedIp::to_ip_addr).min();
+
+        if let Some(ip) = ip {
+            hosts.entry(ip).or_default().push(s);
+        }
+    }
+
+    hosts
+        .into_iter()
+        .filter_map(|(ip, mut services)| {
+            services.sort_by_key(|s| preference(&s.ty_domain));
+
+            let open = services
+                .iter()
+                .filter_map(|s| {
+                    let (_, scheme) = SERVICES.get(preference(&s.ty_domain))?;
+                    Some(Target {
+                        ip,
+                        port: s.port,
+                        scheme: *scheme,
+                    })
+                })
+                .collect::<Vec<Target>>();
+
+            let info = info(&services);
+            let mut device = NetworkDevice::Unidentified;
+            if let Some(ty) = &info.ty {
+                device = device.confirm_printer(ty, "mdns", false);
+            }
+            if let NetworkDevice::Identified(d) = &mut device {
+                if let Some(p) = &mut d.printer {
+                    p.color = info.color.or(p.color);
+                }
+            }
+
+            let mut r = ScanResult::new(ip, *open.first()?, device);
+            r.open = open;
+            r.mdns = Some(info);
+            Some(r)
+        })
+        .collect()
+}
+
+fn preference(ty_domain: &str) -> usize {
+    SERVICES
+        .iter()
+        .position(|(s, _)| *s == ty_domain)
+        .unwrap_or(SERVICES.len())
+}
+
+fn info(services: &[&ResolvedService]) -> DnsSdInfo {
+    // the first service that has a key wins, they're sorted by preference
+    let txt = |key: &str| {
+        services
+            .iter()
+            .find_map(|s| s.get_property_val_str(key))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_owned)
+    };
+    let flag = |key: &str| txt(key).map(|v| v.eq_ignore_ascii_case("t"));
+
+    let instance = services
+        .first()
+        .map(|s| {
+            s.fullname
+                .strip_suffix(&format!(".{}", s.ty_domain))
+                .unwrap_or(&s.fullname)
+        })
+        .map(unescape)
+        .unwrap_or_default();
+
+    DnsSdInfo {
+        instance,
+        hostname: services
+            .first()
+            .map(|s| s.host.trim_end_matches('.').to_owned())
+            .unwrap_or_default(),
+        services: services
+            .iter()
+            .map(|s| s.ty_domain.trim_end_matches(".local.").to_owned())
+            .collect(),
+        ty: txt("ty"),
+        pdl: txt("pdl")
+            .map(|p| p.split(',').map(|f| f.trim().to_owned()).collect())
+            .unwrap_or_default(),
+        color: flag("Color"),
+        duplex: flag("Duplex"),
+        uuid: txt("UUID").map(|u| u.to_lowercase()),
+        rp: txt("rp"),
+    }
+}
+
+/// Instance names come with DNS escapes, `\DDD` for a byte in decimal (e.x. `\032` for a space) and `\` before
+/// a literal character such as `.`
+fn unescape(name: &str) -> String {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+
+        let decimal = rest
+            .get(..3)
+            .filter(|d| d.iter().all(u8::is_ascii_digit))
+            .and_then(|d| std::str::from_utf8(d).ok()?.parse::<u8>().ok());
+
+        match (decimal, rest.split_first()) {
+            (Some(byte), _) => {
+                bytes.push(byte);
+                rest = &rest[3..];
+            }
+            (None, Some((&c, tail))) => {
+                bytes.push(c);
+                rest = tail;
+            }
+            // a trailing backslash escapes nothing
+            (None, None) => bytes.push(b),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdns_sd::ServiceInfo;
+
+    /// A resolved service with the instance name as it comes off the wire
+    fn service(ty_domain: &str, fullname: &str, txt: &[(&str, &str)]) -> ResolvedService {
+        let mut s = ServiceInfo::new(
+            ty_domain,
+            "printer",
+            "NPI3F2A1B.local.",
+            "10.208.2.22",
+            631,
+            txt,
+        )
+        .unwrap()
+        .as_resolved_service();
+        s.fullname = format!("{fullname}.{ty_domain}");
+        s
+    }
+
+    #[test]
+    fn unescapes_instance_names() {
+        assert_eq!(
+            unescape(r"HP\032LaserJet\032Pro\032M402dne\032(3F2A1B)"),
+            "HP LaserJet Pro M402dne (3F2A1B)"
+        );
+        assert_eq!(
+            unescape(r"Brother\032HL-L2350DW\.lib"),
+            "Brother HL-L2350DW.lib"
+        );
+        assert_eq!(unescape(r"back\\slash"), r"back\slash");
+        // multi byte characters come escaped a byte at a time
+        assert_eq!(unescape(r"Caf\195\169"), "Café");
+        // not three digits or out of range, so only the backslash goes
+        assert_eq!(unescape(r"a\32b\999"), "a32b999");
+        assert_eq!(unescape(r"trailing\"), r"trailing\");
+    }
+
+    #[test]
+    fn reads_printer_txt_keys() {
+        let ipp = service(
+            "_ipp._tcp.local.",
+            r"HP\032LaserJet\032Pro\032M402dne\032(3F2A1B)",
+            &[
+                ("ty", "HP LaserJet Pro M402dne"),
+                ("pdl", "application/postscript, image/urf,application/pdf"),
+                ("Color", "F"),
+                ("Duplex", "T"),
+                ("UUID", "1C852A4D-B800-1F08-ABCD-3C52823F2A1B"),
+                ("rp", "ipp/print"),
+                ("note", ""),
+            ],
+        );
+        let pdl = service(
+            "_pdl-datastream._tcp.local.",
+            r"HP\032LaserJet\032Pro\032M402dne\032(3F2A1B)",
+            &[("ty", "HP LaserJet 400"), ("note", "Library")],
+        );
+
+        let info = info(&[&ipp, &pdl]);
+        assert_eq!(info.instance, "HP LaserJet Pro M402dne (3F2A1B)");
+        assert_eq!(info.hostname, "NPI3F2A1B.local");
+        assert_eq!(info.services, ["_ipp._tcp", "_pdl-datastream._tcp"]);
+        // the preferred service's value wins
+        assert_eq!(info.ty.as_deref(), Some("HP LaserJet Pro M402dne"));
+        assert_eq!(
+            info.pdl,
+            ["application/postscript", "image/urf", "application/pdf"]
+        );
+        assert_eq!((info.color, info.duplex), (Some(false), Some(true)));
+        assert_eq!(
+            info.uuid.as_deref(),
+            Some("1c852a4d-b800-1f08-abcd-3c52823f2a1b")
+        );
+        assert_eq!(info.rp.as_deref(), Some("ipp/print"));
+    }
+
+    #[test]
+    fn missing_txt_keys_stay_empty() {
+        let lpd = service("_printer._tcp.local.", "Printer", &[("Color", "")]);
+
+        let info = info(&[&lpd]);
+        assert_eq!(info.ty, None);
+        assert!(info.pdl.is_empty());
+        assert_eq!(
+            (info.color, info.duplex, info.uuid, info.rp),
+            (None, None, None, None)
+        );
+    }
+
+    #[test]
+    fn folds_services_into_one_result() {
+        let services = [
+            service(
+                "_pdl-datastream._tcp.local.",
+                "M402",
+                &[("ty", "HP LaserJet Pro M402dne")],
+            ),
+            service("_ipp._tcp.local.", "M402", &[("Color", "T")]),
+        ];
+
+        let results = results(&services);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].target.scheme, Scheme::Http);
+        assert_eq!(
+            results[0].device.printer().and_then(|p| p.color),
+            Some(true)
+        );
+    }
+}
@@ -0,0 +1,80 @@
+pub mod mdns;
+pub mod wsd;
+
+use crate::{
+    correlate, enrich::Enricher, id::devices::NetworkDevice, output, scanner::ScanResult,
+    DiscoverArgs,
+};
+use anyhow::Result;
+use std::{collections::BTreeMap, net::IpAddr, time::Duration};
+use tokio::time::Instant;
+use wsd::WsdInfo;
+
+/// Finds printers that announce themselves instead of scanning for them, only reaches the local segment
+pub async fn discover_devices(args: DiscoverArgs) -> Result<()> {
+    let enricher = Enricher::new(&args.enrich)?;
+    let deadline = Instant::now() + Duration::from_secs(args.duration);
+
+    println!("Browsing for printers for {}s", args.duration);
+    // either one finding nothing shouldn't lose what the other found
+    let (services, wsd) = tokio::join!(mdns::browse(deadline), wsd::probe(deadline));
+    if let (Err(m), Err(w)) = (&services, &wsd) {
+        anyhow::bail!("mDNS failed: {m:#}, WS-Discovery failed: {w:#}");
+    }
+
+    let mut devices = match services {
+        Ok(services) => mdns::results(&services),
+        Err(e) => {
+            println!("mDNS failed, only using WS-Discovery: {e:#}");
+            vec![]
+        }
+    };
+    match wsd {
+        Ok(wsd) => merge_wsd(&mut devices, wsd),
+        Err(e) => println!("WS-Discovery failed, only using mDNS: {e:#}"),
+    }
+    devices.sort_by_key(|r| r.ip);
+    enricher.enrich(&mut devices).await;
+    let devices = correlate::merge(devices);
+
+    for r in &devices {
+        let capable = match &r.wsd {
+            Some(w) if w.print && w.scan => " (print, scan)",
+            Some(w) if w.print => " (print)",
+            Some(w) if w.scan => " (scan)",
+            _ => "",
+        };
+        println!("{}: {} on {}{capable}", r.name(), r.device, r.target);
+    }
+
+    println!("-- Finished, found {} devices --", devices.len());
+
+    output::write_results(&args.output, args.format, &devices).await?;
+    println!("Successfully wrote to {}", args.output);
+
+    Ok(())
+}
+
+/// Attaches what answered WS-Discovery to the result for its ip, hosts nothing else found get a result of their own
+pub fn merge_wsd(devices: &mut Vec<ScanResult>, wsd: BTreeMap<IpAddr, WsdInfo>) {
+    for (ip, info) in wsd {
+        let Some(target) = info.target(ip) else {
+            println!("{ip} answered WS-Discovery without an address of its own, ignoring it");
+            continue;
+        };
+
+        match devices.iter_mut().find(|r| r.ip == ip) {
+            Some(r) => {
+                if !r.open.contains(&target) {
+                    r.open.push(target);
+                }
+                r.wsd = Some(info);
+            }
+            None => {
+                let mut r = ScanResult::new(ip, target, NetworkDevice::Unidentified);
+                r.wsd = Some(info);
+                devices.push(r);
+            }
+        }
+    }
+}
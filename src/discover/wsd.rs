@@ -0,0 +1,240 @@
+use crate::target::{Scheme, Target};
+use anyhow::Result;
+use regex::Regex;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::OnceLock,
+    time::Duration,
+};
+use tokio::{
+    net::UdpSocket,
+    time::{timeout_at, Instant},
+};
+
+const MULTICAST: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 3702);
+
+/// What a device answered a WS-Discovery probe with
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WsdInfo {
+    /// endpoint reference, usually `urn:uuid:...` and stable across reboots
+    pub endpoint: String,
+    /// e.x. `wsdp:Device`, `wprt:PrintDeviceType`, `wscn:ScanDeviceType`
+    pub types: Vec<String>,
+    /// where the device's metadata can be fetched
+    pub xaddrs: Vec<String>,
+    pub print: bool,
+    pub scan: bool,
+}
+
+impl WsdInfo {
+    /// The xaddr on the host itself, as a target, if any
+    pub fn target(&self, ip: IpAddr) -> Option<Target> {
+        self.xaddrs.iter().find_map(|x| {
+            let url = Url::parse(x).ok()?;
+            if url
+                .host_str()?
+                .trim_matches(['[', ']'])
+                .parse::<IpAddr>()
+                .ok()?
+                != ip
+            {
+                return None;
+            }
+
+            let scheme = match url.scheme() {
+                "https" => Scheme::Https,
+                "http" => Scheme::Http,
+                _ => return None,
+            };
+
+            Some(Target {
+                ip,
+                port: url.port_or_known_default()?,
+                scheme,
+            })
+        })
+    }
+}
+
+fn probe_message(id: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:wsdp="http://schemas.xmlsoap.org/ws/2006/02/devprof">
+<soap:Header>
+<wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To>
+<wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</wsa:Action>
+<wsa:MessageID>{id}</wsa:MessageID>
+</soap:Header>
+<soap:Body><wsd:Probe><wsd:Types>wsdp:Device</wsd:Types></wsd:Probe></soap:Body>
+</soap:Envelope>"#
+    )
+}
+
+/// Multicasts a probe and collects every ProbeMatch until the deadline, one per address
+pub async fn probe(deadline: Instant) -> Result<BTreeMap<IpAddr, WsdInfo>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let id = format!(
+        "urn:uuid:{:08x}-{:04x}-4{:03x}-8{:03x}-{:012x}",
+        rand::random::<u32>(),
+        rand::random::<u16>(),
+        rand::random::<u16>() & 0xfff,
+        rand::random::<u16>() & 0xfff,
+        rand::random::<u64>() & 0xffff_ffff_ffff
+    );
+    let message = probe_message(&id);
+
+    // udp multicast is lossy, the spec has clients repeat the probe
+    for _ in 0..2 {
+        socket.send_to(message.as_bytes(), MULTICAST).await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let mut devices = BTreeMap::new();
+    let mut buf = vec![0; 65_535];
+    while let Ok(res) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        // e.x. an icmp error from an earlier send, only that datagram is lost
+        let Ok((len, from)) = res else {
+            continue;
+        };
+        let body = String::from_utf8_lossy(&buf[..len]);
+
+        if let Some(info) = parse_probe_match(&body, &id) {
+            // every repeated probe is answered again
+            if !devices.contains_key(&from.ip()) {
+                println!("Found {} on {}", info.endpoint, from.ip());
+            }
+            devices.insert(from.ip(), info);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Pulls the first ProbeMatch out of a response, element prefixes differ between vendors so they're ignored
+fn parse_probe_match(body: &str, id: &str) -> Option<WsdInfo> {
+    if element(body, "Action").is_some_and(|a| !a.ends_with("/ProbeMatches")) {
+        return None;
+    }
+    if element(body, "RelatesTo").is_some_and(|r| r != id) {
+        return None;
+    }
+
+    let probe_match = element(body, "ProbeMatch")?;
+    let list = |name: &str| {
+        element(probe_match, name)
+            .map(|v| {
+                v.split_whitespace()
+                    .map(str::to_owned)
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let types = list("Types");
+    let local = |t: &String| t.rsplit(':').next().unwrap_or_default().to_lowercase();
+
+    Some(WsdInfo {
+        endpoint: element(probe_match, "Address")?.to_owned(),
+        print: types.iter().any(|t| local(t).contains("print")),
+        scan: types.iter().any(|t| local(t).contains("scan")),
+        types,
+        xaddrs: list("XAddrs"),
+    })
+}
+
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    static OPEN: OnceLock<Regex> = OnceLock::new();
+    static CLOSE: OnceLock<Regex> = OnceLock::new();
+    let open = OPEN.get_or_init(|| Regex::new(r"<(?:[\w.-]+:)?([\w.-]+)(?:\s[^>]*)?>").unwrap());
+    let close = CLOSE.get_or_init(|| Regex::new(r"</(?:[\w.-]+:)?([\w.-]+)\s*>").unwrap());
+
+    let start = open.captures_iter(xml).find(|c| &c[1] == name)?.get(0)?;
+    let rest = &xml[start.end()..];
+    let end = close.captures_iter(rest).find(|c| &c[1] == name)?.get(0)?;
+
+    Some(rest[..end.start()].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "urn:uuid:0a6dc791-2be6-4991-9af1-454778a1917a";
+
+    /// A ProbeMatches the way HP printers send it, prefixes and all
+    fn probe_matches(relates_to: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:wsdp="http://schemas.xmlsoap.org/ws/2006/02/devprof" xmlns:wprt="http://schemas.microsoft.com/windows/2006/08/wdp/print" xmlns:wscn="http://schemas.microsoft.com/windows/2006/08/wdp/scan">
+<SOAP-ENV:Header>
+<wsa:To>http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</wsa:To>
+<wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/ProbeMatches</wsa:Action>
+<wsa:MessageID>urn:uuid:7d1c9a60-10d5-11f0-8000-3c52823f2a1b</wsa:MessageID>
+<wsa:RelatesTo>{relates_to}</wsa:RelatesTo>
+<wsd:AppSequence InstanceId="41" MessageNumber="2"></wsd:AppSequence>
+</SOAP-ENV:Header>
+<SOAP-ENV:Body>
+<wsd:ProbeMatches>
+<wsd:ProbeMatch>
+<wsa:EndpointReference><wsa:Address>urn:uuid:1c852a4d-b800-1f08-abcd-3c52823f2a1b</wsa:Address></wsa:EndpointReference>
+<wsd:Types>wsdp:Device wscn:ScanDeviceType wprt:PrintDeviceType</wsd:Types>
+<wsd:XAddrs>http://10.208.2.22:3911/ http://[fe80::3e52:82ff:fe3f:2a1b]:3911/</wsd:XAddrs>
+<wsd:MetadataVersion>1</wsd:MetadataVersion>
+</wsd:ProbeMatch>
+</wsd:ProbeMatches>
+</SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn parses_probe_matches() {
+        let info = parse_probe_match(&probe_matches(ID), ID).unwrap();
+
+        assert_eq!(
+            info.endpoint,
+            "urn:uuid:1c852a4d-b800-1f08-abcd-3c52823f2a1b"
+        );
+        assert_eq!(
+            info.types,
+            ["wsdp:Device", "wscn:ScanDeviceType", "wprt:PrintDeviceType"]
+        );
+        assert_eq!(
+            info.xaddrs,
+            [
+                "http://10.208.2.22:3911/",
+                "http://[fe80::3e52:82ff:fe3f:2a1b]:3911/"
+            ]
+        );
+        assert!(info.print && info.scan);
+
+        let target = info.target("10.208.2.22".parse().unwrap()).unwrap();
+        assert_eq!((target.port, target.scheme), (3911, Scheme::Http));
+        let v6 = info.target("fe80::3e52:82ff:fe3f:2a1b".parse().unwrap());
+        assert_eq!(v6.map(|t| t.port), Some(3911));
+        assert!(info.target("10.208.2.23".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn ignores_other_messages() {
+        // an answer to someone else's probe
+        assert!(parse_probe_match(&probe_matches("urn:uuid:other"), ID).is_none());
+        // our own probe looped back
+        assert!(parse_probe_match(&probe_message(ID), ID).is_none());
+        assert!(parse_probe_match("not xml", ID).is_none());
+    }
+
+    #[test]
+    fn finds_elements_regardless_of_prefix() {
+        let xml = r#"<a:Outer><Types>x</Types><b:Types attr="1"> y z </b:Types></a:Outer>"#;
+        assert_eq!(element(xml, "Types"), Some("x"));
+        assert_eq!(
+            element(xml, "Outer"),
+            Some(r#"<Types>x</Types><b:Types attr="1"> y z </b:Types>"#)
+        );
+        assert_eq!(element(xml, "Missing"), None);
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use std::{collections::HashMap, net::IpAddr};
+
+const BUILTIN: &str = include_str!("oui.txt");
+
+/// Entries the kernel never got an answer for are flagged 0x0
+const ATF_COM: u32 = 0x2;
+
+/// Vendors by the first three octets of a MAC address
+pub struct OuiDb {
+    prefixes: HashMap<String, String>,
+}
+
+/// ip to MAC address from the kernel's neighbour table, only hosts on a directly attached segment are in it
+pub fn neighbours() -> Result<HashMap<IpAddr, String>> {
+    let table = std::fs::read_to_string("/proc/net/arp").context("failed to read the arp table")?;
+
+    let neighbours = table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let columns = line.split_whitespace().collect::<Vec<&str>>();
+            let [ip, _, flags, mac, ..] = columns[..] else {
+                return None;
+            };
+
+            let flags = u32::from_str_radix(flags.trim_start_matches("0x"), 16).ok()?;
+            if flags & ATF_COM == 0 || mac == "00:00:00:00:00:00" {
+                return None;
+            }
+
+            Some((ip.parse().ok()?, mac.to_lowercase()))
+        })
+        .collect();
+
+    Ok(neighbours)
+}
+
+impl OuiDb {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN)
+    }
+
+    /// The built-in prefixes, extended by a file in the same format if one is given
+    pub fn with_file(path: Option<&str>) -> Result<Self> {
+        let mut db = Self::builtin();
+
+        if let Some(path) = path {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read oui file {path}"))?;
+            db.prefixes.extend(Self::parse(&content).prefixes);
+        }
+
+        Ok(db)
+    }
+
+    /// `AABBCC Vendor` lines, `#` starts a comment
+    fn parse(content: &str) -> Self {
+        let prefixes = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| {
+                let (prefix, vendor) = l.split_once(char::is_whitespace)?;
+                Some((prefix.to_uppercase(), vendor.trim().to_owned()))
+            })
+            .collect();
+
+        Self { prefixes }
+    }
+
+    pub fn vendor(&self, mac: &str) -> Option<&str> {
+        let prefix = mac
+            .chars()
+            .filter(char::is_ascii_hexdigit)
+            .take(6)
+            .collect::<String>()
+            .to_uppercase();
+
+        self.prefixes.get(&prefix).map(String::as_str)
+    }
+}
+
+/// Vendor names as written by fingerprints and the OUI file, `HP` and `HPE` are close enough
+pub fn same_vendor(a: &str, b: &str) -> bool {
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    a.starts_with(&b) || b.starts_with(&a)
+}
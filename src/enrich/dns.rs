@@ -0,0 +1,73 @@
+use anyhow::Result;
+use hickory_resolver::{
+    config::{NameServerConfig, ResolverConfig},
+    net::runtime::TokioRuntimeProvider,
+    proto::rr::{Name, RData},
+    TokioResolver,
+};
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Duration};
+use tokio::task::JoinSet;
+
+/// Uses the system resolver unless a server is given, e.x. the domain controller that actually has the PTR records
+pub fn resolver(server: Option<IpAddr>, timeout: Duration) -> Result<TokioResolver> {
+    let mut builder = match server {
+        Some(ip) => TokioResolver::builder_with_config(
+            ResolverConfig::from_name_servers(vec![NameServerConfig::udp_and_tcp(ip)]),
+            TokioRuntimeProvider::default(),
+        ),
+        None => TokioResolver::builder_tokio()?,
+    };
+
+    let options = builder.options_mut();
+    options.timeout = timeout;
+    options.attempts = 1;
+
+    Ok(builder.build()?)
+}
+
+/// PTR lookups for every ip with up to `concurrency` in flight, ips without a record are left out
+pub async fn reverse_lookup(
+    resolver: TokioResolver,
+    ips: Vec<IpAddr>,
+    concurrency: usize,
+    timeout: Duration,
+) -> HashMap<IpAddr, String> {
+    let resolver = Arc::new(resolver);
+    let mut queue = ips.into_iter();
+    let mut set = JoinSet::new();
+    let mut names = HashMap::new();
+
+    loop {
+        while set.len() < concurrency.max(1) {
+            let Some(ip) = queue.next() else {
+                break;
+            };
+
+            let resolver = Arc::clone(&resolver);
+            set.spawn(async move {
+                // the resolver's own timeout is per attempt, this caps the whole lookup
+                let lookup = tokio::time::timeout(timeout, resolver.reverse_lookup(Name::from(ip)))
+                    .await
+                    .ok()?
+                    .ok()?;
+
+                let name = lookup.answers().iter().find_map(|r| match &r.data {
+                    RData::PTR(ptr) => Some(ptr.0.to_utf8().trim_end_matches('.').to_owned()),
+                    _ => None,
+                })?;
+
+                Some((ip, name))
+            });
+        }
+
+        let Some(res) = set.join_next().await else {
+            break;
+        };
+
+        if let Ok(Some((ip, name))) = res {
+            names.insert(ip, name);
+        }
+    }
+
+    names
+}
@@ -0,0 +1,122 @@
+mod arp;
+mod dns;
+
+use crate::{id::devices::NetworkDevice, scanner::ScanResult};
+use anyhow::{bail, Result};
+use hickory_resolver::TokioResolver;
+use std::{net::IpAddr, time::Duration};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct EnrichArgs {
+    /// Look up the hostname of every device found
+    #[arg(long)]
+    resolve: bool,
+
+    /// DNS server to look hostnames up with instead of the system resolver
+    #[arg(long)]
+    dns_server: Option<IpAddr>,
+
+    /// Maximum amount of hostname lookups in flight at once
+    #[arg(long, default_value_t = 32)]
+    dns_concurrency: usize,
+
+    /// Timeout for each hostname lookup (in ms)
+    #[arg(long, default_value_t = 1000)]
+    dns_timeout: u64,
+
+    /// Look up the MAC address and NIC vendor of devices on the local segment in the ARP table (Linux only)
+    #[arg(long)]
+    mac: bool,
+
+    /// File of MAC prefixes (`AABBCC Vendor` lines) to extend the built-in ones with
+    #[arg(long)]
+    oui: Option<String>,
+}
+
+/// Fills in what can be learned about devices after they were found, without probing them again.
+/// Created before scanning so a bad `--dns-server` or `--oui` file fails right away instead of after the whole scan
+pub struct Enricher {
+    args: EnrichArgs,
+    resolver: Option<TokioResolver>,
+    oui: Option<arp::OuiDb>,
+}
+
+impl Enricher {
+    pub fn new(args: &EnrichArgs) -> Result<Self> {
+        let timeout = Duration::from_millis(args.dns_timeout);
+        let resolver = args
+            .resolve
+            .then(|| dns::resolver(args.dns_server, timeout))
+            .transpose()?;
+
+        if args.mac && !cfg!(target_os = "linux") {
+            bail!("--mac reads the ARP table from /proc/net/arp, which only Linux has");
+        }
+        let oui = args
+            .mac
+            .then(|| arp::OuiDb::with_file(args.oui.as_deref()))
+            .transpose()?;
+
+        Ok(Self {
+            args: args.clone(),
+            resolver,
+            oui,
+        })
+    }
+
+    /// Anything that can't be looked up is only warned about, the results are worth more than these additions
+    pub async fn enrich(&self, results: &mut [ScanResult]) {
+        let args = &self.args;
+
+        if let Some(resolver) = &self.resolver {
+            let timeout = Duration::from_millis(args.dns_timeout);
+            let ips = results.iter().map(|r| r.ip).collect();
+
+            let names =
+                dns::reverse_lookup(resolver.clone(), ips, args.dns_concurrency, timeout).await;
+            for r in results.iter_mut() {
+                r.hostname = names.get(&r.ip).cloned();
+            }
+
+            println!("Resolved {} of {} hostnames", names.len(), results.len());
+        }
+
+        if let Some(oui) = &self.oui {
+            let neighbours = match arp::neighbours() {
+                Ok(n) => n,
+                Err(e) => {
+                    println!("Skipping MAC addresses: {e:#}");
+                    return;
+                }
+            };
+
+            for r in results.iter_mut() {
+                r.mac = neighbours.get(&r.ip).cloned();
+                r.oui_vendor = r
+                    .mac
+                    .as_deref()
+                    .and_then(|m| oui.vendor(m))
+                    .map(str::to_owned);
+
+                r.vendor_mismatch = match (&r.device, &r.oui_vendor) {
+                    (NetworkDevice::Identified(d), Some(nic)) => !arp::same_vendor(&d.vendor, nic),
+                    _ => false,
+                };
+                if r.vendor_mismatch {
+                    println!(
+                        "{}: identified as {} but the network card is from {}",
+                        r.name(),
+                        r.device,
+                        r.oui_vendor.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+
+            let found = results.iter().filter(|r| r.mac.is_some()).count();
+            println!(
+                "Found the MAC address of {found} of {} devices",
+                results.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::target::Target;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{Display, Formatter},
+    io::Cursor,
+};
+
+/// Hashes of `/favicon.ico`, embedded web UIs often keep the vendor's icon even when the page is a bare redirect
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Favicon {
+    /// signed MurmurHash3 of the base64 of the icon, the hash Shodan and other scanners search by
+    pub mmh3: i32,
+    /// hex sha256 of the icon
+    pub sha256: String,
+}
+
+impl Favicon {
+    pub fn hash(icon: &[u8]) -> Self {
+        // python's base64.encodebytes, which wraps every 76 characters and ends with a newline
+        let encoded = STANDARD.encode(icon);
+        let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+        for line in encoded.as_bytes().chunks(76) {
+            wrapped.push_str(&String::from_utf8_lossy(line));
+            wrapped.push('\n');
+        }
+
+        let mmh3 = murmur3::murmur3_32(&mut Cursor::new(wrapped.as_bytes()), 0).unwrap_or_default();
+
+        Self {
+            mmh3: mmh3 as i32,
+            sha256: format!("{:x}", Sha256::digest(icon)),
+        }
+    }
+}
+
+/// The target's `/favicon.ico`, `None` if there isn't one. Servers that answer every path with their page
+/// don't have one either
+pub async fn fetch(client: &Client, target: &Target) -> Option<Favicon> {
+    let res = client
+        .get(format!("{}favicon.ico", target.url()))
+        .send()
+        .await
+        .ok()?;
+    if !res.status().is_success() {
+        return None;
+    }
+
+    let html = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .is_some_and(|t| t.as_bytes().starts_with(b"text/"));
+    let icon = res.bytes().await.ok()?;
+    if html || icon.is_empty() {
+        return None;
+    }
+
+    Some(Favicon::hash(&icon))
+}
+
+impl Display for Favicon {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("mmh3 {}, sha256 {}", self.mmh3, self.sha256))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_like_other_scanners() {
+        // mmh3.hash(base64.encodebytes(icon)) in python
+        let favicon = Favicon::hash(b"foo");
+        assert_eq!(favicon.mmh3, 851989093);
+        assert_eq!(
+            favicon.sha256,
+            "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae"
+        );
+
+        // long enough to wrap
+        let icon = (0..=255).cycle().take(768).collect::<Vec<u8>>();
+        assert_eq!(Favicon::hash(&icon).mmh3, 1836528006);
+    }
+}
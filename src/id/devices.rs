@@ -1,135 +1,104 @@
-use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize};
 
-use crate::id::PageElement;
-use crate::util::IpWrapper;
+use crate::id::{explain::Identification, printers::PrinterModel};
 
-#[derive(Clone)]
+/// A printer reporting its own model over IPP or SNMP is about as sure as identification gets
+const REPORTED_CONFIDENCE: f32 = 0.95;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
 pub enum NetworkDevice {
-    // version
-    IntegrateDellRemoveAccessController(DellController),
-    CiscoRouter,
-    HpPrinter(Printer),
-    FileMaker,
-    VirataEmWeb,
-    MitsubishiAC,
-    BuildingOperations(BuildingPageType),
-    MiVoice,
-    Fortinet,
+    Identified(Device),
     Unidentified,
 }
 
-#[derive(Debug, Clone)]
-pub enum BuildingPageType {
-    Login,
-    Controller,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Device {
+    /// id of the fingerprint that matched, `ipp` or `snmp` when only that protocol recognized it
+    pub fingerprint: String,
+    pub vendor: String,
+    pub model: String,
+    pub category: Category,
+    /// 0 to 1, see [`Identification::confidence`]
+    pub confidence: f32,
+    pub printer: Option<PrinterModel>,
 }
 
-#[derive(Debug, Clone, Sequence)]
-pub enum Printer {
-    LaserJetMfpM528,
-    LaserJet600M602,
-    OfficeJetPro8702,
-    ColorLaserJetMfpM577,
-    ColorLaserJetM750,
-    LaserJetM402dne,
-    LaserJetM402dn,
-    LaserJetM605,
-    LaserJetProMfpM521dn,
-    ColorLaserJetCp5520Series,
-    LaserJetM506,
-    LaserJetM402n,
-    LaserJetMfpM527,
-    LaserJetMfpM227fdw,
-    LaserJet500MfpM525,
-    ColorLaserJetFlowMfpM681,
-    LaserJetM203dw,
-    LaserJetMfpM426fdw,
-    LaserJetMfpM635,
-    OfficeJetPro8720,
-
-    UnknownJavascriptPrinter,
-    UnknownLaserJet,
-    UnknownOfficeJet,
-}
-
-#[derive(Debug, Clone, Sequence)]
-pub enum DellController {
-    Eight,
-    Nine,
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Printer,
+    Router,
+    Firewall,
+    RemoteManagement,
+    Database,
+    WebServer,
+    Hvac,
+    BuildingAutomation,
+    Voip,
+    Other,
 }
 
 impl NetworkDevice {
-    #[allow(clippy::needless_pass_by_value)]
-    pub fn from_response(ip: &IpWrapper, text: String) -> Self {
-        for t in NetworkDevice::all() {
-            if let Some(e) = t.page_element(ip) {
-                if text.contains(&e) {
-                    return t;
-                }
-            }
-        }
+    pub fn from_identification(id: &Identification<'_>) -> Self {
+        let Some(best) = id.best() else {
+            return Self::Unidentified;
+        };
 
-        if text.contains("HP LaserJet") {
-            return Self::HpPrinter(Printer::UnknownLaserJet);
-        }
+        let f = best.fingerprint;
+        let printer = (f.category == Category::Printer).then(|| {
+            let model = PrinterModel::parse(&format!("{} {}", f.vendor, f.model));
+            model.with_traits(&f.printer.clone().unwrap_or_default())
+        });
 
-        if text.contains("HP OfficeJet") {
-            return Self::HpPrinter(Printer::UnknownOfficeJet);
-        }
+        Self::Identified(Device {
+            fingerprint: f.id.clone(),
+            vendor: f.vendor.clone(),
+            model: f.model.clone(),
+            category: f.category,
+            confidence: id.confidence(),
+            printer,
+        })
+    }
 
-        if text.contains("/framework/Unified.css") {
-            return Self::HpPrinter(Printer::UnknownJavascriptPrinter);
+    pub fn printer(&self) -> Option<&PrinterModel> {
+        match self {
+            Self::Identified(d) => d.printer.as_ref(),
+            Self::Unidentified => None,
         }
-
-        Self::Unidentified
     }
 
-    fn all() -> Vec<Self> {
-        vec![
-            vec![
-                NetworkDevice::IntegrateDellRemoveAccessController(DellController::Eight),
-                NetworkDevice::IntegrateDellRemoveAccessController(DellController::Nine),
-            ],
-            vec![
-                NetworkDevice::BuildingOperations(BuildingPageType::Controller),
-                NetworkDevice::BuildingOperations(BuildingPageType::Login),
-            ],
-            vec![
-                NetworkDevice::CiscoRouter,
-                NetworkDevice::FileMaker,
-                NetworkDevice::MitsubishiAC,
-                NetworkDevice::VirataEmWeb,
-                NetworkDevice::MiVoice,
-                NetworkDevice::Fortinet,
-            ],
-            all::<Printer>()
-                .collect::<Vec<_>>()
-                .into_iter()
-                .map(NetworkDevice::HpPrinter)
-                .collect::<Vec<_>>(),
-        ]
-        .concat()
-    }
-}
+    /// Folds in the make and model a printer reported over `source`, e.x. IPP. The reported model replaces
+    /// guesses from fallback or non-printer fingerprints, a specific printer fingerprint only has its gaps filled
+    pub fn confirm_printer(self, make_and_model: &str, source: &str, fallback: bool) -> Self {
+        let reported = PrinterModel::parse(make_and_model);
 
-impl Printer {
-    #[allow(dead_code)]
-    pub fn is_new(&self) -> bool {
-        matches!(
-            self,
-            Printer::LaserJetMfpM528
-                | Printer::ColorLaserJetFlowMfpM681
-                | Printer::LaserJetMfpM635
-                | Printer::OfficeJetPro8720
-        )
-    }
+        match self {
+            Self::Identified(mut d) if d.category == Category::Printer && !fallback => {
+                let printer = d.printer.get_or_insert_with(|| reported.clone());
+                if printer.family.is_none() {
+                    printer.family = reported.family;
+                }
 
-    pub fn is_unknown(&self) -> bool {
-        matches!(
-            self,
-            Printer::UnknownOfficeJet
-                | Printer::UnknownLaserJet
-                | Printer::UnknownJavascriptPrinter
-        )
+                printer.color = printer.color.or(reported.color);
+                printer.mfp = printer.mfp.or(reported.mfp);
+                Self::Identified(d)
+            }
+            device => {
+                let fingerprint = match device {
+                    Self::Identified(d) => d.fingerprint,
+                    Self::Unidentified => source.to_owned(),
+                };
+
+                Self::Identified(Device {
+                    fingerprint,
+                    vendor: reported.vendor.clone(),
+                    model: reported.model.clone(),
+                    category: Category::Printer,
+                    confidence: REPORTED_CONFIDENCE,
+                    printer: Some(reported),
+                })
+            }
+        }
     }
 }
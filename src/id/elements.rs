@@ -1,60 +1,276 @@
-use crate::id::devices::{BuildingPageType, DellController, NetworkDevice, Printer};
-use crate::id::PageElement;
-use crate::util::IpWrapper;
-
-impl PageElement for NetworkDevice {
-    fn page_element(&self, ip: &IpWrapper) -> Option<String> {
-        let res = match self {
-            NetworkDevice::CiscoRouter => "<script>window.onload=function(){ url ='/webui';window.location.href=url;}</script>",
-            NetworkDevice::FileMaker => "FileMaker Database Server Website",
-            NetworkDevice::VirataEmWeb => "Access Denied. Your IP Address cannot access this device",
-            NetworkDevice::MitsubishiAC => "MITSUBISHI Air Conditioning Control System",
-            NetworkDevice::MiVoice => "MiVoice Office Communications Platform",
-
-            NetworkDevice::HpPrinter(p) => return p.page_element(ip),
-            NetworkDevice::IntegrateDellRemoveAccessController(d) => return d.page_element(ip),
-            NetworkDevice::BuildingOperations(b) => return b.page_element(ip),
-            NetworkDevice::Fortinet => return Some(format!("<a href=\"https://{ip}/ng\">here</a>.</p")),
-
-            NetworkDevice::Unidentified => "",
+use crate::response::CapturedResponse;
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    fmt::{Display, Formatter},
+    net::IpAddr,
+};
+
+/// A single check against a scanned page, `{ip}` in any plain text pattern is replaced with the scanned ip
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// the body contains this text
+    Body(String),
+    BodyRegex(Pattern),
+    /// the `<title>` contains this text
+    Title(String),
+    TitleRegex(Pattern),
+    /// the status code is any of these
+    Status(Vec<u16>),
+    /// the header is present, and if given its value contains the text or matches the regex
+    Header {
+        name: String,
+        contains: Option<String>,
+        regex: Option<Pattern>,
+    },
+    /// the field of the TLS certificate contains the text or matches the regex, a san matching is enough
+    Cert {
+        field: CertField,
+        contains: Option<String>,
+        regex: Option<Pattern>,
+    },
+    /// `/favicon.ico` has these hashes, only when probing favicons
+    Favicon {
+        mmh3: Option<i32>,
+        sha256: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CertField {
+    Subject,
+    Issuer,
+    San,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "String")]
+pub struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Regex::new(&value).map(Pattern)
+    }
+}
+
+impl Rule {
+    /// Why the rule matched, or why it didn't
+    pub fn check(&self, ip: &IpAddr, res: &CapturedResponse) -> Result<String, String> {
+        let template = |s: &str| s.replace("{ip}", &ip.to_string());
+        let title = res.title.as_deref();
+
+        match self {
+            Rule::Body(b) => {
+                if res.body.contains(&template(b)) {
+                    Ok(String::from("found in body"))
+                } else {
+                    Err(String::from("not in body"))
+                }
+            }
+            Rule::BodyRegex(Pattern(r)) => r
+                .find(&res.body)
+                .map(|m| format!("matched {:?}", truncate(m.as_str())))
+                .ok_or_else(|| String::from("no match in body")),
+            Rule::Title(t) => match title {
+                Some(title) if title.contains(&template(t)) => Ok(format!("title is {title:?}")),
+                Some(title) => Err(format!("title is {title:?}")),
+                None => Err(String::from("no title")),
+            },
+            Rule::TitleRegex(Pattern(r)) => match title {
+                Some(title) if r.is_match(title) => Ok(format!("title is {title:?}")),
+                Some(title) => Err(format!("title is {title:?}")),
+                None => Err(String::from("no title")),
+            },
+            Rule::Status(s) => {
+                if s.contains(&res.status) {
+                    Ok(format!("status is {}", res.status))
+                } else {
+                    Err(format!("status is {}", res.status))
+                }
+            }
+            Rule::Header {
+                name,
+                contains,
+                regex,
+            } => {
+                let mut values = res.headers(name).peekable();
+                if values.peek().is_none() {
+                    return Err(format!("no {name} header"));
+                }
+
+                let mut seen = vec![];
+                for v in values {
+                    if contains.as_ref().is_none_or(|c| v.contains(&template(c)))
+                        && regex.as_ref().is_none_or(|Pattern(r)| r.is_match(v))
+                    {
+                        return Ok(format!("{name}: {v}"));
+                    }
+
+                    seen.push(v);
+                }
+
+                Err(format!("{name}: {}", seen.join(", ")))
+            }
+            Rule::Cert {
+                field,
+                contains,
+                regex,
+            } => {
+                let Some(cert) = &res.cert else {
+                    return Err(String::from("no certificate"));
+                };
+
+                let values = match field {
+                    CertField::Subject => vec![cert.subject.as_str()],
+                    CertField::Issuer => vec![cert.issuer.as_str()],
+                    CertField::San => cert.sans.iter().map(String::as_str).collect(),
+                };
+
+                match values.iter().find(|v| {
+                    contains.as_ref().is_none_or(|c| v.contains(&template(c)))
+                        && regex.as_ref().is_none_or(|Pattern(r)| r.is_match(v))
+                }) {
+                    Some(v) => Ok(format!("{field} is {v:?}")),
+                    None if values.is_empty() => Err(format!("no {field}")),
+                    None => Err(format!("{field} is {:?}", values.join(", "))),
+                }
+            }
+            Rule::Favicon { mmh3, sha256 } => {
+                let Some(favicon) = &res.favicon else {
+                    return Err(String::from("no favicon"));
+                };
+
+                if mmh3.is_none_or(|h| h == favicon.mmh3)
+                    && sha256
+                        .as_ref()
+                        .is_none_or(|h| h.eq_ignore_ascii_case(&favicon.sha256))
+                {
+                    Ok(format!("favicon {favicon}"))
+                } else {
+                    Err(format!("favicon {favicon}"))
+                }
+            }
+        }
+    }
+
+    /// Rules that would match every response with the field, they'd only add points to whatever else matched
+    pub fn validate(&self) -> Result<(), String> {
+        let empty = match self {
+            Rule::Body(t) | Rule::Title(t) => t.is_empty(),
+            Rule::BodyRegex(_) | Rule::TitleRegex(_) => false,
+            Rule::Status(s) => s.is_empty(),
+            Rule::Header {
+                contains, regex, ..
+            }
+            | Rule::Cert {
+                contains, regex, ..
+            } => contains.as_deref().is_none_or(str::is_empty) && regex.is_none(),
+            Rule::Favicon { mmh3, sha256 } => mmh3.is_none() && sha256.is_none(),
         };
 
-        if res.is_empty() {
-            None
+        if empty {
+            Err(format!("`{self}` has nothing to match"))
         } else {
-            Some(res.to_owned())
+            Ok(())
         }
     }
-}
 
-impl PageElement for BuildingPageType {
-    fn page_element(&self, _ip: &IpWrapper) -> Option<String> {
-        let s = match self {
-            BuildingPageType::Login => "<button type=\"submit\" id=\"login\"></button></label>",
-            BuildingPageType::Controller => "h5.02c.518 0 .918-.187 1.255-.56.12-.147.28",
-        };
+    /// How much a match of this rule says about the device, longer and more targeted patterns count more
+    pub fn points(&self) -> f32 {
+        let len = |s: &str| s.chars().count().min(40) as f32;
 
-        Some(s.to_owned())
+        match self {
+            Rule::Body(b) => len(b),
+            Rule::BodyRegex(_) => 20.0,
+            Rule::Title(t) => 10.0 + len(t),
+            Rule::TitleRegex(_) => 25.0,
+            Rule::Status(_) => 2.0,
+            Rule::Header {
+                contains, regex, ..
+            }
+            | Rule::Cert {
+                contains, regex, ..
+            } => {
+                5.0 + contains.as_deref().map_or(0.0, len)
+                    + if regex.is_some() { 15.0 } else { 0.0 }
+            }
+            // an exact hash, but vendors reuse one icon across all their models, so page text naming one wins
+            Rule::Favicon { .. } => 10.0,
+        }
     }
 }
 
-impl PageElement for DellController {
-    fn page_element(&self, ip: &IpWrapper) -> Option<String> {
-        let s = match self {
-            DellController::Eight => format!("<a href=\"https://{}/start.html\">here</a>", ip.0),
-            DellController::Nine => format!("<a href=\"https://{}/restgui/start.html\">here</a>", ip.0)
-        };
+fn truncate(s: &str) -> String {
+    if s.chars().count() > 60 {
+        format!("{}...", s.chars().take(57).collect::<String>())
+    } else {
+        s.to_owned()
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Body(b) => f.write_fmt(format_args!("body contains {:?}", truncate(b))),
+            Rule::BodyRegex(Pattern(r)) => f.write_fmt(format_args!("body matches /{r}/")),
+            Rule::Title(t) => f.write_fmt(format_args!("title contains {t:?}")),
+            Rule::TitleRegex(Pattern(r)) => f.write_fmt(format_args!("title matches /{r}/")),
+            Rule::Status(s) => f.write_fmt(format_args!("status in {s:?}")),
+            Rule::Header {
+                name,
+                contains,
+                regex,
+            } => {
+                f.write_fmt(format_args!("header {name}"))?;
+                if let Some(c) = contains {
+                    f.write_fmt(format_args!(" contains {c:?}"))?;
+                }
+                if let Some(Pattern(r)) = regex {
+                    f.write_fmt(format_args!(" matches /{r}/"))?;
+                }
+
+                Ok(())
+            }
+            Rule::Cert {
+                field,
+                contains,
+                regex,
+            } => {
+                f.write_fmt(format_args!("certificate {field}"))?;
+                if let Some(c) = contains {
+                    f.write_fmt(format_args!(" contains {c:?}"))?;
+                }
+                if let Some(Pattern(r)) = regex {
+                    f.write_fmt(format_args!(" matches /{r}/"))?;
+                }
 
-        Some(s)
+                Ok(())
+            }
+            Rule::Favicon { mmh3, sha256 } => {
+                f.write_str("favicon")?;
+                if let Some(h) = mmh3 {
+                    f.write_fmt(format_args!(" mmh3 {h}"))?;
+                }
+                if let Some(h) = sha256 {
+                    f.write_fmt(format_args!(" sha256 {h}"))?;
+                }
+
+                Ok(())
+            }
+        }
     }
 }
 
-impl PageElement for Printer {
-    fn page_element(&self, _ip: &IpWrapper) -> Option<String> {
-        if self.is_unknown() {
-            None
-        } else {
-            Some(self.to_string())
+impl Display for CertField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CertField::Subject => f.write_str("subject"),
+            CertField::Issuer => f.write_str("issuer"),
+            CertField::San => f.write_str("san"),
         }
     }
 }
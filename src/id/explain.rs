@@ -0,0 +1,217 @@
+use crate::id::{elements::Rule, fingerprint::Fingerprint};
+use std::fmt::{Display, Formatter};
+
+/// How well a single fingerprint fits a response
+pub struct Candidate<'a> {
+    pub fingerprint: &'a Fingerprint,
+    /// every rule alongside why it did or didn't match
+    pub rules: Vec<(&'a Rule, Result<String, String>)>,
+    pub score: f32,
+}
+
+impl Candidate<'_> {
+    pub fn matched(&self) -> bool {
+        self.rules.iter().all(|(_, r)| r.is_ok())
+    }
+}
+
+/// Every fingerprint with at least one matching rule, best first
+pub struct Identification<'a> {
+    pub candidates: Vec<Candidate<'a>>,
+}
+
+impl<'a> Identification<'a> {
+    pub fn new(mut candidates: Vec<Candidate<'a>>) -> Self {
+        // full matches first, fallbacks only win when nothing specific matched, then by score.
+        // the sort is stable so file order still breaks exact ties
+        candidates.sort_by(|a, b| {
+            b.matched()
+                .cmp(&a.matched())
+                .then(a.fingerprint.fallback.cmp(&b.fingerprint.fallback))
+                .then(b.score.total_cmp(&a.score))
+        });
+
+        Self { candidates }
+    }
+
+    pub fn best(&self) -> Option<&Candidate<'a>> {
+        self.candidates.first().filter(|c| c.matched())
+    }
+
+    /// Grows with the score of the best match and shrinks when another fingerprint of the same kind scored close to it
+    pub fn confidence(&self) -> f32 {
+        let Some(best) = self.best() else {
+            return 0.0;
+        };
+
+        let strength = 1.0 - 0.5f32.powf(best.score / 8.0);
+        let runner_up = self
+            .candidates
+            .iter()
+            .skip(1)
+            .find(|c| c.matched() && c.fingerprint.fallback == best.fingerprint.fallback)
+            .map_or(0.0, |c| (c.score / best.score).min(1.0));
+
+        let confidence = strength * (1.0 - runner_up / 2.0);
+        if best.fingerprint.fallback {
+            confidence / 2.0
+        } else {
+            confidence
+        }
+    }
+}
+
+impl Display for Identification<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.candidates.is_empty() {
+            return f.write_str("  no fingerprint had a matching rule\n");
+        }
+
+        for (i, c) in self.candidates.iter().enumerate() {
+            let marker = match (i, c.matched()) {
+                (0, true) => "*",
+                (_, true) => "+",
+                _ => "-",
+            };
+            let fp = c.fingerprint;
+            let hits = c.rules.iter().filter(|(_, r)| r.is_ok()).count();
+
+            f.write_fmt(format_args!(
+                "  {marker} {} ({} {}) score {:.1}, {hits}/{} rules{}\n",
+                fp.id,
+                fp.vendor,
+                fp.model,
+                c.score,
+                c.rules.len(),
+                if fp.fallback { ", fallback" } else { "" }
+            ))?;
+
+            for (rule, res) in &c.rules {
+                match res {
+                    Ok(why) => f.write_fmt(format_args!("      + {rule}: {why}\n"))?,
+                    Err(why) => f.write_fmt(format_args!("      - {rule}: {why}\n"))?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{id::fingerprint::FingerprintDb, response::CapturedResponse};
+    use std::net::IpAddr;
+
+    fn page(body: &str) -> CapturedResponse {
+        CapturedResponse::new(200, vec![], body.to_owned())
+    }
+
+    fn ip() -> IpAddr {
+        "10.208.2.22".parse().unwrap()
+    }
+
+    fn db(fingerprints: &str) -> FingerprintDb {
+        toml::from_str(fingerprints).unwrap()
+    }
+
+    const DB: &str = r#"
+        [[fingerprint]]
+        id = "generic"
+        vendor = "HP"
+        model = "Unknown Printer"
+        category = "printer"
+        fallback = true
+        rules = [{ body = "Hewlett-Packard Embedded Web Server" }]
+
+        [[fingerprint]]
+        id = "first"
+        vendor = "HP"
+        model = "LaserJet"
+        category = "printer"
+        rules = [{ body = "LaserJet" }]
+
+        [[fingerprint]]
+        id = "second"
+        vendor = "HP"
+        model = "LaserJet Too"
+        category = "printer"
+        rules = [{ body = "LaserJet" }]
+
+        [[fingerprint]]
+        id = "partial"
+        vendor = "HP"
+        model = "Color LaserJet"
+        category = "printer"
+        rules = [{ body = "Color LaserJet Enterprise" }, { status = [401] }]
+    "#;
+
+    #[test]
+    fn longer_model_wins_over_its_prefix() {
+        let db = FingerprintDb::builtin();
+
+        let dne = db.identify(&ip(), &page("<h1>HP LaserJet M402dne</h1>"));
+        assert_eq!(dne.best().unwrap().fingerprint.id, "hp-laserjet-m402dne");
+        // the m402dn rule matched too, which costs confidence
+        assert!(dne
+            .candidates
+            .iter()
+            .any(|c| c.matched() && c.fingerprint.id == "hp-laserjet-m402dn"));
+
+        let dn = db.identify(&ip(), &page("<h1>HP LaserJet M402dn</h1>"));
+        assert_eq!(dn.best().unwrap().fingerprint.id, "hp-laserjet-m402dn");
+        assert!(dn.confidence() > dne.confidence());
+    }
+
+    #[test]
+    fn fallbacks_lose_to_any_specific_match() {
+        let db = db(DB);
+
+        // the fallback's rule is longer and scores more, it still comes second
+        let id = db.identify(
+            &ip(),
+            &page("Hewlett-Packard Embedded Web Server, LaserJet"),
+        );
+        assert_eq!(id.best().unwrap().fingerprint.id, "first");
+        assert!(id.candidates.last().unwrap().fingerprint.fallback);
+
+        let id = db.identify(&ip(), &page("Hewlett-Packard Embedded Web Server"));
+        let best = id.best().unwrap();
+        assert_eq!(best.fingerprint.id, "generic");
+        // a fallback is never more than half sure
+        assert!(id.confidence() <= 0.5);
+    }
+
+    #[test]
+    fn partial_matches_never_win() {
+        let db = db(DB);
+
+        // partial scores more than first and second but misses the status rule
+        let id = db.identify(&ip(), &page("Color LaserJet Enterprise"));
+        assert_eq!(id.best().unwrap().fingerprint.id, "first");
+        let partial = id.candidates.last().unwrap();
+        assert_eq!(partial.fingerprint.id, "partial");
+        assert!(!partial.matched());
+
+        // only the status rule matches, nothing is identified
+        let id = db.identify(&ip(), &CapturedResponse::new(401, vec![], String::new()));
+        assert_eq!(id.candidates.len(), 1);
+        assert!(id.best().is_none());
+        assert_eq!(id.confidence(), 0.0);
+    }
+
+    #[test]
+    fn ties_go_to_file_order_at_lower_confidence() {
+        let db = db(DB);
+
+        let id = db.identify(&ip(), &page("LaserJet"));
+        assert_eq!(id.best().unwrap().fingerprint.id, "first");
+        assert_eq!(id.candidates[1].fingerprint.id, "second");
+
+        // an equal runner up halves the confidence of the same score alone
+        let mut alone = db.clone();
+        alone.fingerprints.retain(|f| f.id != "second");
+        let sure = alone.identify(&ip(), &page("LaserJet")).confidence();
+        assert!((id.confidence() - sure / 2.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    id::{
+        devices::Category,
+        elements::Rule,
+        explain::{Candidate, Identification},
+        printers::PrinterTraits,
+    },
+    response::CapturedResponse,
+};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::net::IpAddr;
+
+const BUILTIN: &str = include_str!("fingerprints.toml");
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Fingerprint {
+    pub id: String,
+    pub vendor: String,
+    pub model: String,
+    pub category: Category,
+    pub rules: Vec<Rule>,
+    /// only used when no regular fingerprint matches
+    #[serde(default)]
+    pub fallback: bool,
+    /// details of printers that can't be guessed from the model
+    pub printer: Option<PrinterTraits>,
+}
+
+impl Fingerprint {
+    pub fn evaluate(&self, ip: &IpAddr, res: &CapturedResponse) -> Candidate<'_> {
+        let rules = self
+            .rules
+            .iter()
+            .map(|r| (r, r.check(ip, res)))
+            .collect::<Vec<_>>();
+        let score = rules
+            .iter()
+            .filter(|(_, res)| res.is_ok())
+            .map(|(r, _)| r.points())
+            .sum();
+
+        Candidate {
+            fingerprint: self,
+            rules,
+            score,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FingerprintDb {
+    #[serde(rename = "fingerprint", default)]
+    pub fingerprints: Vec<Fingerprint>,
+}
+
+impl FingerprintDb {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("built-in fingerprints are invalid")
+    }
+
+    /// The built-in fingerprints extended or overridden by an optional file
+    pub fn with_file(path: Option<&str>) -> Result<Self> {
+        let mut db = Self::builtin();
+
+        if let Some(path) = path {
+            db.extend(Self::load(path)?);
+        }
+
+        Ok(db)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read fingerprints from {path}"))?;
+
+        Self::parse(&content).with_context(|| format!("invalid fingerprints in {path}"))
+    }
+
+    pub fn identify(&self, ip: &IpAddr, res: &CapturedResponse) -> Identification<'_> {
+        Identification::new(
+            self.fingerprints
+                .iter()
+                .map(|f| f.evaluate(ip, res))
+                .filter(|c| c.score > 0.0)
+                .collect(),
+        )
+    }
+
+    fn parse(content: &str) -> Result<Self> {
+        let db = toml::from_str::<Self>(content)?;
+
+        for f in &db.fingerprints {
+            if f.rules.is_empty() {
+                bail!("fingerprint {} has no rules", f.id);
+            }
+
+            for rule in &f.rules {
+                if let Err(e) = rule.validate() {
+                    bail!("fingerprint {}: {e}", f.id);
+                }
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Replaces fingerprints sharing an id in place, new ones are checked before all existing ones
+    pub fn extend(&mut self, other: Self) {
+        let mut new = vec![];
+
+        for f in other.fingerprints {
+            match self.fingerprints.iter_mut().find(|e| e.id == f.id) {
+                Some(existing) => *existing = f,
+                None => new.push(f),
+            }
+        }
+
+        new.append(&mut self.fingerprints);
+        self.fingerprints = new;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rules: &str) -> Result<FingerprintDb> {
+        FingerprintDb::parse(&format!(
+            "[[fingerprint]]\nid = \"test\"\nvendor = \"Acme\"\nmodel = \"Box\"\n\
+             category = \"other\"\nrules = {rules}\n"
+        ))
+    }
+
+    #[test]
+    fn rejects_rules_without_criteria() {
+        for rules in [
+            "[]",
+            r#"[{ body = "" }]"#,
+            "[{ status = [] }]",
+            r#"[{ header = { name = "server" } }]"#,
+            r#"[{ header = { name = "server", contains = "" } }]"#,
+            r#"[{ cert = { field = "subject" } }]"#,
+            "[{ favicon = {} }]",
+        ] {
+            assert!(parse(rules).is_err(), "{rules}");
+        }
+
+        for rules in [
+            r#"[{ header = { name = "server", contains = "EWS" } }]"#,
+            r#"[{ cert = { field = "san", regex = "^NPI" } }]"#,
+            "[{ favicon = { mmh3 = 1 } }]",
+        ] {
+            assert!(parse(rules).is_ok(), "{rules}");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    capture::{self, SavedResponse},
+    id::fingerprint::FingerprintDb,
+    response::CapturedResponse,
+    FingerprintTestArgs,
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt::{Display, Formatter},
+    net::IpAddr,
+    path::Path,
+};
+
+/// A response saved by `scan --save-unidentified` with an `# expect: <fingerprint id>` line added
+pub struct Fixture {
+    pub name: String,
+    pub ip: IpAddr,
+    /// id of the fingerprint, `None` for `# expect: unidentified`
+    pub expect: Option<String>,
+    pub response: CapturedResponse,
+}
+
+impl Fixture {
+    fn from_saved(saved: SavedResponse) -> Result<Self> {
+        let expect = match saved.meta("expect").context("missing # expect")? {
+            "unidentified" => None,
+            id => Some(id.to_owned()),
+        };
+
+        Ok(Self {
+            expect,
+            name: saved.name,
+            ip: saved.ip,
+            response: saved.response,
+        })
+    }
+}
+
+/// Every `.txt` file in the directory, by name
+pub fn load_dir(dir: &Path) -> Result<Vec<Fixture>> {
+    capture::load_dir(dir)?
+        .into_iter()
+        .map(|saved| {
+            let name = saved.name.clone();
+            Fixture::from_saved(saved).with_context(|| format!("invalid fixture {name}"))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub passed: usize,
+    /// fixtures identified as something else, with the explanation
+    pub failures: Vec<String>,
+    /// fixtures where the winner only won by coming first in the file
+    pub conflicts: Vec<String>,
+    /// fingerprints no fixture expects
+    pub uncovered: Vec<String>,
+}
+
+impl Report {
+    pub fn ok(&self) -> bool {
+        self.failures.is_empty() && self.conflicts.is_empty() && self.uncovered.is_empty()
+    }
+}
+
+pub fn run(db: &FingerprintDb, fixtures: &[Fixture]) -> Report {
+    let mut report = Report::default();
+
+    for fixture in fixtures {
+        let id = db.identify(&fixture.ip, &fixture.response);
+        let best = id.best();
+        let got = best.map(|c| c.fingerprint.id.as_str());
+
+        if got != fixture.expect.as_deref() {
+            report.failures.push(format!(
+                "{}: expected {}, got {}\n{id}",
+                fixture.name,
+                fixture.expect.as_deref().unwrap_or("unidentified"),
+                got.unwrap_or("unidentified")
+            ));
+            continue;
+        }
+
+        let tied = best.and_then(|best| {
+            id.candidates.iter().skip(1).find(|c| {
+                c.matched()
+                    && c.fingerprint.fallback == best.fingerprint.fallback
+                    && c.score == best.score
+            })
+        });
+        match tied {
+            Some(c) => report.conflicts.push(format!(
+                "{}: {} only beats {} by coming first",
+                fixture.name,
+                got.unwrap_or_default(),
+                c.fingerprint.id
+            )),
+            None => report.passed += 1,
+        }
+    }
+
+    report.uncovered = db
+        .fingerprints
+        .iter()
+        .filter(|f| {
+            !fixtures
+                .iter()
+                .any(|x| x.expect.as_deref() == Some(f.id.as_str()))
+        })
+        .map(|f| f.id.clone())
+        .collect();
+
+    report
+}
+
+pub fn test_fingerprints(args: &FingerprintTestArgs) -> Result<()> {
+    let db = FingerprintDb::with_file(args.fingerprints.as_deref())?;
+    let fixtures = load_dir(Path::new(&args.dir))?;
+    let report = run(&db, &fixtures);
+
+    print!("{report}");
+    if !report.ok() {
+        bail!("fingerprints don't match their fixtures");
+    }
+
+    Ok(())
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for failure in &self.failures {
+            f.write_fmt(format_args!("FAIL {failure}"))?;
+        }
+        for conflict in &self.conflicts {
+            f.write_fmt(format_args!("CONFLICT {conflict}\n"))?;
+        }
+        for id in &self.uncovered {
+            f.write_fmt(format_args!("NO SAMPLE {id}\n"))?;
+        }
+
+        f.write_fmt(format_args!(
+            "{} passed, {} failed, {} conflicts, {} fingerprints without a sample\n",
+            self.passed,
+            self.failures.len(),
+            self.conflicts.len(),
+            self.uncovered.len()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> Vec<Fixture> {
+        load_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")).unwrap()
+    }
+
+    #[test]
+    fn fixtures_identify_as_expected() {
+        let report = run(&FingerprintDb::builtin(), &fixtures());
+
+        assert!(report.failures.is_empty(), "{report}");
+        assert!(report.conflicts.is_empty(), "{report}");
+    }
+
+    #[test]
+    fn every_fingerprint_has_a_fixture() {
+        let report = run(&FingerprintDb::builtin(), &fixtures());
+
+        assert!(report.uncovered.is_empty(), "{report}");
+    }
+}
@@ -1,9 +1,7 @@
-use crate::util::IpWrapper;
-
 pub mod devices;
 mod display;
 mod elements;
-
-pub trait PageElement {
-    fn page_element(&self, ip: &IpWrapper) -> Option<String>;
-}
+pub mod explain;
+pub mod fingerprint;
+pub mod fixtures;
+pub mod printers;
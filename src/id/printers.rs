@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+
+/// A printer described independently of where it was recognized, so page fingerprints,
+/// IPP and SNMP can all produce one
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrinterModel {
+    pub vendor: String,
+    /// product line, e.x. LaserJet, imageRUNNER or `WorkCentre`
+    pub family: Option<String>,
+    /// full model without the vendor, e.x. `Color LaserJet FlowMFP M681`
+    pub model: String,
+    pub color: Option<bool>,
+    pub mfp: Option<bool>,
+}
+
+/// Explicit printer details in a fingerprint, anything left out is guessed from the model
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PrinterTraits {
+    pub family: Option<String>,
+    pub color: Option<bool>,
+    pub mfp: Option<bool>,
+}
+
+struct Family {
+    name: &'static str,
+    inkjet: bool,
+    mfp: bool,
+    color: bool,
+}
+
+const fn family(name: &'static str, inkjet: bool, mfp: bool, color: bool) -> Family {
+    Family {
+        name,
+        inkjet,
+        mfp,
+        color,
+    }
+}
+
+/// Spellings vendors use for themselves, mapped to one name
+const VENDORS: &[(&str, &str)] = &[
+    ("hewlett-packard", "HP"),
+    ("hewlett packard", "HP"),
+    ("hp", "HP"),
+    ("canon", "Canon"),
+    ("fuji xerox", "Xerox"),
+    ("xerox", "Xerox"),
+    ("ricoh", "Ricoh"),
+    ("brother", "Brother"),
+    ("lexmark", "Lexmark"),
+    ("kyocera mita", "Kyocera"),
+    ("kyocera", "Kyocera"),
+    ("epson", "Epson"),
+    ("konica minolta", "Konica Minolta"),
+    ("sharp", "Sharp"),
+    ("toshiba", "Toshiba"),
+    ("samsung", "Samsung"),
+];
+
+/// Product lines per vendor, longer names first so `Color LaserJet` wins over `LaserJet`
+const FAMILIES: &[(&str, &[Family])] = &[
+    (
+        "HP",
+        &[
+            family("Color LaserJet", false, false, true),
+            family("LaserJet", false, false, false),
+            family("OfficeJet", true, false, true),
+            family("DeskJet", true, false, true),
+            family("PageWide", true, false, true),
+            family("DesignJet", true, false, true),
+            family("ENVY", true, true, true),
+            family("Smart Tank", true, true, true),
+        ],
+    ),
+    (
+        "Canon",
+        &[
+            family("imageRUNNER", false, true, false),
+            family("imageCLASS", false, false, false),
+            family("i-SENSYS", false, false, false),
+            family("imagePROGRAF", true, false, true),
+            family("PIXMA", true, false, true),
+            family("MAXIFY", true, false, true),
+        ],
+    ),
+    (
+        "Xerox",
+        &[
+            family("AltaLink", false, true, false),
+            family("VersaLink", false, false, false),
+            family("WorkCentre", false, true, false),
+            family("PrimeLink", false, true, true),
+            family("Phaser", false, false, false),
+            family("ColorQube", false, false, true),
+        ],
+    ),
+    (
+        "Ricoh",
+        &[
+            family("Aficio", false, false, false),
+            family("IM", false, true, false),
+            family("MP", false, true, false),
+            family("SP", false, false, false),
+        ],
+    ),
+    (
+        "Brother",
+        &[
+            family("MFC", false, true, false),
+            family("DCP", false, true, false),
+            family("HL", false, false, false),
+        ],
+    ),
+    (
+        "Lexmark",
+        &[
+            family("CX", false, true, true),
+            family("XC", false, true, true),
+            family("MC", false, true, true),
+            family("CS", false, false, true),
+            family("MX", false, true, false),
+            family("XM", false, true, false),
+            family("MB", false, true, false),
+            family("MS", false, false, false),
+        ],
+    ),
+    (
+        "Kyocera",
+        &[
+            family("TASKalfa", false, true, false),
+            family("ECOSYS", false, false, false),
+            family("FS", false, false, false),
+        ],
+    ),
+    (
+        "Epson",
+        &[
+            family("WorkForce", true, false, true),
+            family("EcoTank", true, false, true),
+            family("Expression", true, false, true),
+            family("SureColor", true, false, true),
+        ],
+    ),
+    ("Konica Minolta", &[family("bizhub", false, true, false)]),
+];
+
+const MFP_WORDS: &[&str] = &["mfp", "flowmfp", "all-in-one", "aio"];
+const COLOR_WORDS: &[&str] = &["color", "colour"];
+
+impl PrinterModel {
+    /// Parses a make and model string, e.x. `HP Color LaserJet FlowMFP M681` from a page
+    /// or IPP `printer-make-and-model`
+    pub fn parse(make_and_model: &str) -> Self {
+        let make_and_model = make_and_model.trim();
+        let lower = make_and_model.to_lowercase();
+
+        let (vendor, model) = VENDORS
+            .iter()
+            .find(|(alias, _)| starts_with_word(&lower, alias))
+            .map_or_else(
+                || match make_and_model.split_once(' ') {
+                    Some((v, m)) => (v.to_owned(), m.trim()),
+                    None => (make_and_model.to_owned(), ""),
+                },
+                |(alias, name)| {
+                    let model = make_and_model.get(alias.len()..).unwrap_or_default();
+                    ((*name).to_owned(), model.trim())
+                },
+            );
+
+        let family = FAMILIES
+            .iter()
+            .find(|(v, _)| *v == vendor)
+            .and_then(|(_, families)| families.iter().find(|f| has_family(model, f.name)));
+
+        let words = model
+            .split(|c: char| c.is_whitespace() || c == '_')
+            .map(str::to_lowercase)
+            .collect::<Vec<String>>();
+        let has_word = |list: &[&str]| words.iter().any(|w| list.contains(&w.as_str()));
+
+        let color = match family {
+            _ if has_word(COLOR_WORDS) => Some(true),
+            Some(f) => Some(f.color || f.inkjet),
+            None => None,
+        };
+        let mfp = match family {
+            _ if has_word(MFP_WORDS) => Some(true),
+            Some(f) => Some(f.mfp),
+            None => None,
+        };
+
+        Self {
+            vendor,
+            family: family.map(|f| f.name.to_owned()),
+            model: model.to_owned(),
+            color,
+            mfp,
+        }
+    }
+
+    pub fn with_traits(mut self, traits: &PrinterTraits) -> Self {
+        if traits.family.is_some() {
+            self.family.clone_from(&traits.family);
+        }
+
+        self.color = traits.color.or(self.color);
+        self.mfp = traits.mfp.or(self.mfp);
+        self
+    }
+}
+
+fn starts_with_word(s: &str, prefix: &str) -> bool {
+    s.starts_with(prefix)
+        && s[prefix.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !c.is_alphanumeric())
+}
+
+/// The family has to start a word and be followed by a separator or the model number,
+/// so `MFC-L8900CDW` and `CX725` match but `MPEG` doesn't match `MP`
+fn has_family(model: &str, family: &str) -> bool {
+    let lower = model.to_lowercase();
+    let family = family.to_lowercase();
+
+    lower.match_indices(&family).any(|(i, _)| {
+        let word_start = lower[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric());
+        let after = lower[i + family.len()..].chars().next();
+
+        word_start && after.is_none_or(|c| !c.is_alphabetic())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// vendor, family, model, color, mfp
+    fn parsed(s: &str) -> (String, Option<String>, String, Option<bool>, Option<bool>) {
+        let p = PrinterModel::parse(s);
+        (p.vendor, p.family, p.model, p.color, p.mfp)
+    }
+
+    fn vendor_and_family(s: &str) -> (String, Option<String>) {
+        let p = PrinterModel::parse(s);
+        (p.vendor, p.family)
+    }
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_owned())
+    }
+
+    #[test]
+    fn parses_hp_models() {
+        assert_eq!(
+            parsed("HP Color LaserJet FlowMFP M681"),
+            (
+                "HP".into(),
+                some("Color LaserJet"),
+                "Color LaserJet FlowMFP M681".into(),
+                Some(true),
+                Some(true)
+            )
+        );
+        assert_eq!(
+            parsed("HP LaserJet Pro M402dne"),
+            (
+                "HP".into(),
+                some("LaserJet"),
+                "LaserJet Pro M402dne".into(),
+                Some(false),
+                Some(false)
+            )
+        );
+        assert_eq!(
+            parsed("Hewlett-Packard LaserJet MFP M528"),
+            (
+                "HP".into(),
+                some("LaserJet"),
+                "LaserJet MFP M528".into(),
+                Some(false),
+                Some(true)
+            )
+        );
+        assert_eq!(
+            vendor_and_family("HP OfficeJet Pro 9025e"),
+            ("HP".into(), some("OfficeJet"))
+        );
+        assert_eq!(
+            PrinterModel::parse("HP OfficeJet Pro 9025e").color,
+            Some(true)
+        );
+
+        // not a spelling of HP
+        assert_eq!(vendor_and_family("HPE ProLiant").0, "HPE");
+    }
+
+    #[test]
+    fn parses_other_vendors() {
+        for (make_and_model, vendor, family_name) in [
+            (
+                "Canon imageRUNNER ADVANCE 4545",
+                "Canon",
+                some("imageRUNNER"),
+            ),
+            ("Canon imageCLASS MF445dw", "Canon", some("imageCLASS")),
+            ("Xerox WorkCentre 6515", "Xerox", some("WorkCentre")),
+            ("FUJI XEROX DocuPrint CP225 w", "Xerox", None),
+            ("Xerox VersaLink C405", "Xerox", some("VersaLink")),
+            ("RICOH IM C3000", "Ricoh", some("IM")),
+            ("Ricoh MP C3004ex", "Ricoh", some("MP")),
+            ("Brother MFC-L8900CDW series", "Brother", some("MFC")),
+            ("Brother HL-L2350DW series", "Brother", some("HL")),
+            ("Lexmark CX725", "Lexmark", some("CX")),
+            ("Lexmark MS811", "Lexmark", some("MS")),
+            ("KYOCERA MITA FS-C5100DN", "Kyocera", some("FS")),
+            ("Kyocera ECOSYS M2540dn", "Kyocera", some("ECOSYS")),
+            ("Kyocera TASKalfa 3252ci", "Kyocera", some("TASKalfa")),
+        ] {
+            assert_eq!(
+                vendor_and_family(make_and_model),
+                (vendor.to_owned(), family_name),
+                "{make_and_model}"
+            );
+        }
+
+        let brother = PrinterModel::parse("Brother MFC-L8900CDW series");
+        assert_eq!(brother.model, "MFC-L8900CDW series");
+        assert_eq!(brother.mfp, Some(true));
+        let lexmark = PrinterModel::parse("Lexmark CX725");
+        assert_eq!((lexmark.color, lexmark.mfp), (Some(true), Some(true)));
+        let kyocera = PrinterModel::parse("Kyocera ECOSYS M2540dn");
+        assert_eq!((kyocera.color, kyocera.mfp), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn unknown_vendors_keep_the_first_word() {
+        assert_eq!(
+            parsed("Acme Printomatic 3000"),
+            ("Acme".into(), None, "Printomatic 3000".into(), None, None)
+        );
+        // a family needs a word boundary, MPEG isn't Ricoh's MP
+        assert_eq!(vendor_and_family("Ricoh MPEG Encoder").1, None);
+    }
+}
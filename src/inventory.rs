@@ -0,0 +1,598 @@
+use crate::{id::devices::NetworkDevice, scanner::ScanResult, target::TargetSpec, HistoryArgs};
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    fmt::{Display, Formatter},
+    net::IpAddr,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    targets TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS scan_ranges (
+    scan INTEGER NOT NULL REFERENCES scans(id),
+    start TEXT NOT NULL,
+    end TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS observations (
+    id INTEGER PRIMARY KEY,
+    ip TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    scheme TEXT NOT NULL,
+    fingerprint TEXT,
+    device TEXT NOT NULL,
+    response_hash TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL,
+    first_scan INTEGER NOT NULL REFERENCES scans(id),
+    last_scan INTEGER NOT NULL REFERENCES scans(id)
+);
+
+CREATE INDEX IF NOT EXISTS observations_ip ON observations(ip);
+";
+
+/// Every scan and what it saw, an observation stretches over consecutive scans that saw the same thing on an ip
+pub struct Inventory {
+    conn: Connection,
+}
+
+struct Scan {
+    id: i64,
+    started_at: String,
+    /// inclusive ranges the scan probed, discovered hosts outside the targets are one address each
+    ranges: Vec<(IpAddr, IpAddr)>,
+}
+
+struct Observation {
+    id: i64,
+    port: u16,
+    scheme: String,
+    device: String,
+    response_hash: String,
+    first_scan: i64,
+    last_scan: i64,
+}
+
+/// A stretch of scans covering an ip that all saw the same thing
+pub enum Period {
+    Seen {
+        from: String,
+        to: String,
+        scans: usize,
+        target: String,
+        device: String,
+        response_hash: String,
+        moved_from: Option<String>,
+    },
+    Offline {
+        from: String,
+        to: String,
+        scans: usize,
+        moved_to: Option<String>,
+    },
+}
+
+pub struct History {
+    pub ip: IpAddr,
+    pub periods: Vec<Period>,
+}
+
+fn timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl Inventory {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn =
+            Connection::open(path).with_context(|| format!("failed to open inventory {path}"))?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self { conn })
+    }
+
+    fn observations(&self, ip: IpAddr) -> Result<Vec<Observation>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, port, scheme, device, response_hash, first_scan, last_scan
+             FROM observations WHERE ip = ?1 ORDER BY first_scan, id",
+        )?;
+
+        let observations = stmt
+            .query_map([ip.to_string()], |row| {
+                Ok(Observation {
+                    id: row.get(0)?,
+                    port: row.get(1)?,
+                    scheme: row.get(2)?,
+                    device: row.get(3)?,
+                    response_hash: row.get(4)?,
+                    first_scan: row.get(5)?,
+                    last_scan: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Observation>>>()?;
+
+        Ok(observations)
+    }
+
+    pub fn record_scan(
+        &mut self,
+        targets: &TargetSpec,
+        started_at: DateTime<Utc>,
+        results: &[ScanResult],
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let previous = scans(&tx)?;
+
+        tx.execute(
+            "INSERT INTO scans (started_at, finished_at, targets) VALUES (?1, ?2, ?3)",
+            params![
+                timestamp(started_at),
+                timestamp(Utc::now()),
+                targets.to_string()
+            ],
+        )?;
+        let scan = tx.last_insert_rowid();
+
+        let ranges = targets
+            .ranges()
+            .map(|(start, end)| (IpAddr::V4(start), IpAddr::V4(end)))
+            .collect::<Vec<_>>();
+        let discovered = results
+            .iter()
+            .map(|r| (r.ip, r.ip))
+            .filter(|(ip, _)| !in_ranges(&ranges, *ip));
+
+        for (start, end) in ranges.iter().copied().chain(discovered) {
+            tx.execute(
+                "INSERT INTO scan_ranges (scan, start, end) VALUES (?1, ?2, ?3)",
+                params![scan, start.to_string(), end.to_string()],
+            )?;
+        }
+
+        for r in results {
+            let ip = r.ip.to_string();
+            let seen = timestamp(r.seen_at);
+            let device = r.device.to_string();
+            let fingerprint = match &r.device {
+                NetworkDevice::Identified(d) => Some(d.fingerprint.as_str()),
+                NetworkDevice::Unidentified => None,
+            };
+
+            // only extend the last observation if nothing else was seen in between, otherwise it would hide the gap
+            let last_covering = previous
+                .iter()
+                .rev()
+                .find(|s| covers(s, r.ip))
+                .map(|s| s.id);
+
+            let latest = tx
+                .query_row(
+                    "SELECT id, port, scheme, device, response_hash, last_scan FROM observations
+                     WHERE ip = ?1 ORDER BY last_scan DESC, id DESC LIMIT 1",
+                    [&ip],
+                    |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, u16>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                            row.get::<_, String>(4)?,
+                            row.get::<_, i64>(5)?,
+                        ))
+                    },
+                )
+                .optional()?;
+
+            let unchanged = latest.as_ref().filter(|(_, port, scheme, d, hash, last)| {
+                *port == r.target.port
+                    && *scheme == r.target.scheme.to_string()
+                    && *d == device
+                    && *hash == r.response_hash
+                    && Some(*last) == last_covering
+            });
+
+            match unchanged {
+                Some((id, ..)) => {
+                    tx.execute(
+                        "UPDATE observations SET last_seen = ?1, last_scan = ?2 WHERE id = ?3",
+                        params![seen, scan, id],
+                    )?;
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO observations
+                         (ip, port, scheme, fingerprint, device, response_hash, first_seen, last_seen, first_scan, last_scan)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?8, ?8)",
+                        params![
+                            ip,
+                            r.target.port,
+                            r.target.scheme.to_string(),
+                            fingerprint,
+                            device,
+                            r.response_hash,
+                            seen,
+                            scan
+                        ],
+                    )?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every scan that covered the ip, grouped into what it saw
+    pub fn history(&self, ip: IpAddr) -> Result<History> {
+        let observations = self.observations(ip)?;
+        let scans = scans(&self.conn)?
+            .into_iter()
+            .filter(|s| covers(s, ip))
+            .collect::<Vec<Scan>>();
+
+        let mut periods: Vec<(Option<&Observation>, Vec<&Scan>)> = vec![];
+        for s in &scans {
+            let seen = observations
+                .iter()
+                .find(|o| o.first_scan <= s.id && s.id <= o.last_scan);
+
+            match periods.last_mut() {
+                Some((o, group)) if o.map(|o| o.id) == seen.map(|o| o.id) => group.push(s),
+                _ => periods.push((seen, vec![s])),
+            }
+        }
+
+        let periods = periods
+            .into_iter()
+            .map(|(o, group)| {
+                let from = group[0].started_at.clone();
+                let to = group[group.len() - 1].started_at.clone();
+                let first = group[0].id;
+
+                Ok(match o {
+                    Some(o) => Period::Seen {
+                        from,
+                        to,
+                        scans: group.len(),
+                        target: format!("{}/{}", o.port, o.scheme),
+                        device: o.device.clone(),
+                        response_hash: o.response_hash.clone(),
+                        moved_from: self.moved(ip, &o.device, first, true)?,
+                    },
+                    None => Period::Offline {
+                        from,
+                        to,
+                        scans: group.len(),
+                        moved_to: observations
+                            .iter()
+                            .rev()
+                            .find(|o| o.last_scan < first)
+                            .map(|o| self.moved(ip, &o.device, first, false))
+                            .transpose()?
+                            .flatten(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<Period>>>()?;
+
+        Ok(History { ip, periods })
+    }
+
+    /// Another ip that the same identified device was last seen on right before, or first seen on right after, the scan.
+    /// Devices are only told apart by what they were identified as, so it has to be the only one of its kind in both
+    /// scans, two of the same model would otherwise look like they swap places
+    fn moved(&self, ip: IpAddr, device: &str, scan: i64, before: bool) -> Result<Option<String>> {
+        let previous =
+            self.conn
+                .query_row("SELECT MAX(id) FROM scans WHERE id < ?1", [scan], |row| {
+                    row.get::<_, Option<i64>>(0)
+                })?;
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
+
+        let ip = ip.to_string();
+        let (was, is) = (self.seen_on(device, previous)?, self.seen_on(device, scan)?);
+        let moved = match (&was[..], &is[..]) {
+            ([was], [is]) if before && *is == ip && *was != ip => Some(was.clone()),
+            ([was], [is]) if !before && *was == ip && *is != ip => Some(is.clone()),
+            _ => None,
+        };
+
+        Ok(moved)
+    }
+
+    /// Every ip the identified device was seen on in the scan
+    fn seen_on(&self, device: &str, scan: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT ip FROM observations WHERE device = ?1 AND fingerprint IS NOT NULL
+             AND first_scan <= ?2 AND ?2 <= last_scan",
+        )?;
+
+        let ips = stmt
+            .query_map(params![device, scan], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(ips)
+    }
+}
+
+fn scans(conn: &Connection) -> Result<Vec<Scan>> {
+    let mut stmt = conn.prepare("SELECT id, started_at FROM scans ORDER BY id")?;
+    let mut scans = stmt
+        .query_map([], |row| {
+            Ok(Scan {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                ranges: vec![],
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<Scan>>>()?;
+
+    let mut stmt = conn.prepare("SELECT scan, start, end FROM scan_ranges")?;
+    let ranges = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (scan, start, end) in ranges {
+        let (Ok(start), Ok(end)) = (start.parse(), end.parse()) else {
+            continue;
+        };
+
+        if let Some(s) = scans.iter_mut().find(|s| s.id == scan) {
+            s.ranges.push((start, end));
+        }
+    }
+
+    Ok(scans)
+}
+
+fn covers(scan: &Scan, ip: IpAddr) -> bool {
+    in_ranges(&scan.ranges, ip)
+}
+
+fn in_ranges(ranges: &[(IpAddr, IpAddr)], ip: IpAddr) -> bool {
+    ranges
+        .iter()
+        .any(|(start, end)| start.is_ipv4() == ip.is_ipv4() && *start <= ip && ip <= *end)
+}
+
+pub fn print_history(args: &HistoryArgs) -> Result<()> {
+    let inventory = Inventory::open(&args.inventory)?;
+    print!("{}", inventory.history(args.ip)?);
+
+    Ok(())
+}
+
+impl Display for History {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.periods.is_empty() {
+            return f.write_fmt(format_args!("no scan has covered {}\n", self.ip));
+        }
+
+        f.write_fmt(format_args!("history of {}\n", self.ip))?;
+
+        for p in &self.periods {
+            match p {
+                Period::Seen {
+                    from,
+                    to,
+                    scans,
+                    target,
+                    device,
+                    response_hash,
+                    moved_from,
+                } => {
+                    f.write_fmt(format_args!(
+                        "  {from} - {to}  {device} on {target}, body {} ({scans} scans)\n",
+                        &response_hash[..response_hash.len().min(12)]
+                    ))?;
+                    if let Some(m) = moved_from {
+                        f.write_fmt(format_args!("      possibly moved here from {m}\n"))?;
+                    }
+                }
+                Period::Offline {
+                    from,
+                    to,
+                    scans,
+                    moved_to,
+                } => {
+                    f.write_fmt(format_args!("  {from} - {to}  not found ({scans} scans)\n"))?;
+                    if let Some(m) = moved_to {
+                        f.write_fmt(format_args!("      possibly moved to {m}\n"))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        id::devices::{Category, Device},
+        target::{Scheme, Target},
+    };
+    use chrono::TimeZone;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, d, 9, 0, 0).unwrap()
+    }
+
+    fn seen(ip: &str, fingerprint: &str, d: u32) -> ScanResult {
+        let ip = ip.parse().unwrap();
+        let target = Target {
+            ip,
+            port: 443,
+            scheme: Scheme::Https,
+        };
+        let device = NetworkDevice::Identified(Device {
+            fingerprint: fingerprint.to_owned(),
+            vendor: String::from("HP"),
+            model: fingerprint.to_owned(),
+            category: Category::Printer,
+            confidence: 0.5,
+            printer: None,
+        });
+
+        ScanResult {
+            seen_at: day(d),
+            ..ScanResult::new(ip, target, device)
+        }
+    }
+
+    /// from, to and scans of every period, `None` device when offline
+    fn timeline(inventory: &Inventory, ip: &str) -> Vec<(String, String, usize, Option<String>)> {
+        inventory
+            .history(ip.parse().unwrap())
+            .unwrap()
+            .periods
+            .into_iter()
+            .map(|p| match p {
+                Period::Seen {
+                    from,
+                    to,
+                    scans,
+                    device,
+                    ..
+                } => (from, to, scans, Some(device)),
+                Period::Offline {
+                    from, to, scans, ..
+                } => (from, to, scans, None),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn records_first_and_last_seen_and_gaps() {
+        let mut inventory = Inventory::open(":memory:").unwrap();
+        let subnet = "10.208.2.0/24".parse().unwrap();
+        let other = "10.208.3.0/24".parse().unwrap();
+
+        inventory
+            .record_scan(&subnet, day(1), &[seen("10.208.2.22", "m402", 1)])
+            .unwrap();
+        // a scan of somewhere else doesn't break the stretch
+        inventory.record_scan(&other, day(2), &[]).unwrap();
+        inventory
+            .record_scan(&subnet, day(3), &[seen("10.208.2.22", "m402", 3)])
+            .unwrap();
+        inventory.record_scan(&subnet, day(4), &[]).unwrap();
+        inventory
+            .record_scan(&subnet, day(5), &[seen("10.208.2.22", "m402", 5)])
+            .unwrap();
+
+        let device = Some(String::from("HP m402 (Printer)"));
+        assert_eq!(
+            timeline(&inventory, "10.208.2.22"),
+            [
+                (timestamp(day(1)), timestamp(day(3)), 2, device.clone()),
+                (timestamp(day(4)), timestamp(day(4)), 1, None),
+                (timestamp(day(5)), timestamp(day(5)), 1, device),
+            ]
+        );
+
+        let (first_seen, last_seen) = inventory
+            .conn
+            .query_row(
+                "SELECT first_seen, last_seen FROM observations WHERE ip = '10.208.2.22' ORDER BY id LIMIT 1",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (first_seen, last_seen),
+            (timestamp(day(1)), timestamp(day(3)))
+        );
+
+        assert!(timeline(&inventory, "10.208.9.9").is_empty());
+    }
+
+    /// moved from and moved to of every period
+    fn moves(inventory: &Inventory, ip: &str) -> Vec<Option<String>> {
+        inventory
+            .history(ip.parse().unwrap())
+            .unwrap()
+            .periods
+            .into_iter()
+            .map(|p| match p {
+                Period::Seen { moved_from, .. } => moved_from,
+                Period::Offline { moved_to, .. } => moved_to,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn only_one_of_a_kind_moves() {
+        let mut inventory = Inventory::open(":memory:").unwrap();
+        let subnet = "10.208.2.0/24".parse().unwrap();
+
+        inventory
+            .record_scan(&subnet, day(1), &[seen("10.208.2.22", "m402", 1)])
+            .unwrap();
+        inventory
+            .record_scan(&subnet, day(2), &[seen("10.208.2.40", "m402", 2)])
+            .unwrap();
+        assert_eq!(
+            moves(&inventory, "10.208.2.22"),
+            [None, Some(String::from("10.208.2.40"))]
+        );
+        assert_eq!(
+            moves(&inventory, "10.208.2.40"),
+            [None, Some(String::from("10.208.2.22"))]
+        );
+
+        // two of the same model, one goes offline while the other's page changes
+        let mut inventory = Inventory::open(":memory:").unwrap();
+        inventory
+            .record_scan(
+                &subnet,
+                day(1),
+                &[
+                    seen("10.208.2.22", "m402", 1),
+                    seen("10.208.2.23", "m402", 1),
+                ],
+            )
+            .unwrap();
+        let mut changed = seen("10.208.2.23", "m402", 2);
+        changed.response_hash = String::from("changed");
+        inventory.record_scan(&subnet, day(2), &[changed]).unwrap();
+
+        assert_eq!(moves(&inventory, "10.208.2.22"), [None, None]);
+        assert_eq!(moves(&inventory, "10.208.2.23"), [None, None]);
+    }
+
+    #[test]
+    fn discovered_ipv6_hosts_have_history() {
+        let mut inventory = Inventory::open(":memory:").unwrap();
+        let subnet = "10.208.2.0/24".parse().unwrap();
+
+        for d in 1..=2 {
+            inventory
+                .record_scan(
+                    &subnet,
+                    day(d),
+                    &[seen("fe80::3e52:82ff:fe3f:2a1b", "m402", d)],
+                )
+                .unwrap();
+        }
+
+        let periods = timeline(&inventory, "fe80::3e52:82ff:fe3f:2a1b");
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].2, 2);
+    }
+}
@@ -1,20 +1,46 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+use crate::archive::reidentify;
+use crate::cluster::suggest_fingerprints;
+use crate::diff::diff_files;
+use crate::discover::discover_devices;
+use crate::enrich::EnrichArgs;
+use crate::id::fixtures::test_fingerprints;
+use crate::inventory::print_history;
+use crate::output::OutputFormat;
 use crate::printing::print_ipp;
-use crate::scanner::scan_for_devices;
+use crate::scanner::{identify_device, scan_for_devices};
+use crate::snmp::SnmpVersion;
+use crate::target::{Scheme, TargetSpec};
+use std::net::IpAddr;
 
+mod archive;
+mod capture;
+mod cert;
+mod cluster;
+mod correlate;
+mod diff;
+mod discover;
+mod enrich;
+mod favicon;
 mod id;
+mod inventory;
+mod output;
+mod pjl;
 mod printer;
 mod printing;
+mod response;
 mod scanner;
+mod snmp;
+mod target;
 mod threads;
 mod util;
 
 #[derive(clap::Args, Debug, Clone)]
 #[command(long_about = None)]
 pub struct ScannerArgs {
-    /// Amount of threads to simultaneously request on
+    /// Maximum amount of requests in flight at once
     #[arg(short, long, default_value_t = 20)]
     threads: usize,
 
@@ -22,21 +48,220 @@ pub struct ScannerArgs {
     #[arg(short, long)]
     verbose: bool,
 
-    /// The subnet to generate ips for (use x to denote a wildcard)
-    #[arg(short, long, default_value_t = String::from("10.208.x.x"))]
-    ip_subnet: String,
+    /// Comma separated ips to scan, e.x. "10.208.x.x", "10.208.16.0/20", "10.208.2.10-90", "!10.208.5.0/24"
+    #[arg(short, long, default_value = "10.208.x.x")]
+    ip_subnet: TargetSpec,
 
     /// Display a progress bar
     #[arg(short, long, default_value_t = true)]
     progress_bar: bool,
 
-    /// Append ips to file after each success instead of all at the end
+    /// Append devices to the output after each success as well, the output is rewritten with the merged
+    /// devices once the scan finishes
     #[arg(short, long)]
     append_file: bool,
 
+    /// File to write found devices to
+    #[arg(short, long, default_value_t = String::from("./devices.txt"))]
+    output: String,
+
+    /// Format of the output file
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Print which fingerprint rules matched for every device
+    #[arg(long)]
+    explain: bool,
+
+    /// Output of a previous scan to print the differences to once finished
+    #[arg(long)]
+    baseline: Option<String>,
+
+    /// SQLite database to keep the history of every scan in, created if missing
+    #[arg(long)]
+    inventory: Option<String>,
+
+    /// Directory to save the responses no fingerprint matched to, for writing new fingerprints
+    #[arg(long)]
+    save_unidentified: Option<String>,
+
+    /// File to record every response to, so the scan can be identified again offline with `reidentify`
+    #[arg(long)]
+    record: Option<String>,
+
+    /// Multicast a WS-Discovery probe while scanning and fold in what answers, devices outside the
+    /// scanned ips included. Only reaches the local segment
+    #[arg(long)]
+    wsd: bool,
+
+    #[command(flatten)]
+    probe: ProbeArgs,
+
+    #[command(flatten)]
+    enrich: EnrichArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ProbeArgs {
+    /// Comma separated ports to probe on every host, all at once, the first in order that answers
+    /// identifies it. 631 is left to the IPP probe with `--ipp`
+    #[arg(long, value_delimiter = ',', default_value = "443,80,8080,631")]
+    ports: Vec<u16>,
+
+    /// Schemes to try on each port, in order, until one responds
+    #[arg(long, value_delimiter = ',', default_value = "https,http")]
+    schemes: Vec<Scheme>,
+
     /// Timeout for scanning (in ms)
     #[arg(long, default_value_t = 2000)]
     timeout: u64,
+
+    /// Fingerprint file (toml) to extend or override the built-in fingerprints with
+    #[arg(long)]
+    fingerprints: Option<String>,
+
+    /// Ask every host for its printer attributes over IPP on port 631, trying ipps when plain IPP isn't served.
+    /// Hosts that only answer IPP are still reported
+    #[arg(long)]
+    ipp: bool,
+
+    /// Read the Printer MIB of every host that responds over SNMP, even ones none of the ports answered on
+    #[arg(long)]
+    snmp: bool,
+
+    /// SNMP community to read with
+    #[arg(long, default_value = "public")]
+    snmp_community: String,
+
+    /// SNMP version to read with
+    #[arg(long, value_enum, default_value_t = SnmpVersion::V2c)]
+    snmp_version: SnmpVersion,
+
+    /// Ask printers from vendors known to speak PJL for their id and status on port 9100
+    #[arg(long)]
+    pjl: bool,
+
+    /// Fetch /favicon.ico from the identified port and hash it for favicon rules
+    #[arg(long)]
+    favicon: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct IdentifyArgs {
+    /// ip of the device to identify
+    ip: IpAddr,
+
+    #[command(flatten)]
+    probe: ProbeArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct DiscoverArgs {
+    /// How long to listen for announcements (in seconds)
+    #[arg(short, long, default_value_t = 5)]
+    duration: u64,
+
+    /// File to write found devices to
+    #[arg(short, long, default_value_t = String::from("./devices.txt"))]
+    output: String,
+
+    /// Format of the output file
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    #[command(flatten)]
+    enrich: EnrichArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct DiffArgs {
+    /// output of the older scan, in any format
+    old: String,
+
+    /// output of the newer scan, in any format
+    new: String,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct ReidentifyArgs {
+    /// file written by scan --record
+    archive: String,
+
+    /// Fingerprint file (toml) to extend or override the built-in fingerprints with
+    #[arg(long)]
+    fingerprints: Option<String>,
+
+    /// File to write the devices to
+    #[arg(short, long, default_value_t = String::from("./devices.txt"))]
+    output: String,
+
+    /// Format of the output file
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Print which fingerprint rules matched for every device
+    #[arg(long)]
+    explain: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct FingerprintsArgs {
+    #[command(subcommand)]
+    action: FingerprintsAction,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum FingerprintsAction {
+    /// Check that every fixture is identified as the fingerprint it expects and every fingerprint has one
+    Test(FingerprintTestArgs),
+    /// Group the responses no fingerprint matched and propose a rule for each group
+    Suggest(FingerprintSuggestArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct FingerprintTestArgs {
+    /// directory of fixtures, responses saved by scan --save-unidentified with an `# expect: <id>` line
+    dir: String,
+
+    /// Fingerprint file (toml) to extend or override the built-in fingerprints with
+    #[arg(long)]
+    fingerprints: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct FingerprintSuggestArgs {
+    /// directory written by scan --save-unidentified, or a file written by scan --record
+    source: String,
+
+    /// Fingerprint file (toml) to extend or override the built-in fingerprints with
+    #[arg(long)]
+    fingerprints: Option<String>,
+
+    /// Only show groups of at least this many hosts
+    #[arg(long, default_value_t = 2)]
+    min_size: usize,
+
+    /// Share of words two bodies need in common to be grouped, from 0 to 1
+    #[arg(long, default_value_t = 0.8)]
+    similarity: f32,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(long_about = None)]
+pub struct HistoryArgs {
+    /// ip to show the timeline of
+    ip: IpAddr,
+
+    /// SQLite database written by scan --inventory
+    #[arg(long)]
+    inventory: String,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -75,8 +300,20 @@ struct Args {
 
 #[derive(Subcommand, Debug, Clone)]
 enum Action {
-    Scan(ScannerArgs),
+    Scan(Box<ScannerArgs>),
+    /// Probe a single device and explain how it was identified
+    Identify(IdentifyArgs),
+    /// Find printers on the local segment that announce themselves over mDNS or WS-Discovery
+    Discover(DiscoverArgs),
     Print(PrintArgs),
+    /// Compare the results of two scans
+    Diff(DiffArgs),
+    /// Show what every scan in an inventory saw on an ip
+    History(HistoryArgs),
+    /// Identify the devices of a recorded scan again, e.x. with new fingerprints, without touching the network
+    Reidentify(ReidentifyArgs),
+    /// Work on fingerprints
+    Fingerprints(FingerprintsArgs),
 }
 
 #[tokio::main]
@@ -84,7 +321,16 @@ async fn main() -> Result<()> {
     let args = Args::parse();
 
     match args.action {
-        Action::Scan(s) => scan_for_devices(s).await,
+        Action::Scan(s) => scan_for_devices(*s).await,
+        Action::Identify(i) => identify_device(i).await,
+        Action::Discover(d) => discover_devices(d).await,
         Action::Print(p) => print_ipp(p).await,
+        Action::Diff(d) => diff_files(&d),
+        Action::History(h) => print_history(&h),
+        Action::Reidentify(r) => reidentify(r).await,
+        Action::Fingerprints(f) => match f.action {
+            FingerprintsAction::Test(t) => test_fingerprints(&t),
+            FingerprintsAction::Suggest(s) => suggest_fingerprints(s).await,
+        },
     }
 }
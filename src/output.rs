@@ -0,0 +1,792 @@
+use crate::{
+    cert::CertInfo,
+    discover::{mdns::DnsSdInfo, wsd::WsdInfo},
+    favicon::Favicon,
+    id::{
+        devices::{Category, Device, NetworkDevice},
+        printers::PrinterModel,
+    },
+    pjl::PjlInfo,
+    printer::IppInfo,
+    scanner::ScanResult,
+    snmp::{SnmpInfo, Supply},
+    target::{Scheme, Target},
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `ip:device` lines, followed by ` | name` for devices with a hostname
+    Text,
+    Json,
+    /// one json record per line
+    Ndjson,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Guesses which format a file was written in from its content
+    pub fn detect(content: &str) -> Self {
+        let content = content.trim_start();
+
+        // text output starts with `[` too when the first device is on an ipv6 address
+        let array = content
+            .strip_prefix('[')
+            .map(|rest| rest.trim_start().starts_with(['{', ']']) || rest.trim().is_empty());
+
+        if array == Some(true) {
+            Self::Json
+        } else if content.starts_with('{') {
+            Self::Ndjson
+        } else if content.starts_with("ip,") {
+            Self::Csv
+        } else {
+            Self::Text
+        }
+    }
+}
+
+/// Formats results one at a time, so the same output can be written all at once or appended as devices are found
+pub struct RecordEncoder {
+    format: OutputFormat,
+    written: usize,
+}
+
+/// Flattened [`ScanResult`] since csv can't nest. Lists are separated by `;`, a `\` escapes a separator
+/// inside an item
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CsvRow {
+    pub ip: String,
+    pub port: u16,
+    pub scheme: String,
+    /// every open target as `port/scheme`, or `ip:port/scheme` when on an alias, separated by `;`
+    pub open: String,
+    /// other ips of the same device, separated by `;`
+    #[serde(default)]
+    pub aliases: String,
+    pub fingerprint: String,
+    pub vendor: String,
+    pub model: String,
+    pub category: Option<Category>,
+    pub confidence: Option<f32>,
+    pub printer_family: String,
+    pub printer_color: Option<bool>,
+    pub printer_mfp: Option<bool>,
+    #[serde(default)]
+    pub response_hash: String,
+    pub seen_at: String,
+    #[serde(default)]
+    pub cert_subject: String,
+    #[serde(default)]
+    pub cert_issuer: String,
+    /// separated by `;`
+    #[serde(default)]
+    pub cert_sans: String,
+    #[serde(default)]
+    pub cert_not_before: String,
+    #[serde(default)]
+    pub cert_not_after: String,
+    #[serde(default)]
+    pub cert_sha256: String,
+    #[serde(default)]
+    pub favicon_mmh3: Option<i32>,
+    #[serde(default)]
+    pub favicon_sha256: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub web_hostname: String,
+    #[serde(default)]
+    pub mac: String,
+    #[serde(default)]
+    pub oui_vendor: String,
+    #[serde(default)]
+    pub vendor_mismatch: bool,
+    #[serde(default)]
+    pub ipp_make_and_model: String,
+    #[serde(default)]
+    pub ipp_name: String,
+    #[serde(default)]
+    pub ipp_location: String,
+    #[serde(default)]
+    pub ipp_dns_sd_name: String,
+    #[serde(default)]
+    pub ipp_uuid: String,
+    #[serde(default)]
+    pub snmp_sys_descr: String,
+    #[serde(default)]
+    pub snmp_device_descr: String,
+    #[serde(default)]
+    pub snmp_serial: String,
+    #[serde(default)]
+    pub snmp_page_count: Option<u64>,
+    /// every supply as `description:level/max capacity`, separated by `;`
+    #[serde(default)]
+    pub snmp_supplies: String,
+    #[serde(default)]
+    pub pjl_id: String,
+    #[serde(default)]
+    pub pjl_status_code: Option<u32>,
+    #[serde(default)]
+    pub pjl_display: String,
+    #[serde(default)]
+    pub pjl_online: Option<bool>,
+    #[serde(default)]
+    pub mdns_instance: String,
+    #[serde(default)]
+    pub mdns_hostname: String,
+    /// separated by `;`
+    #[serde(default)]
+    pub mdns_services: String,
+    #[serde(default)]
+    pub mdns_ty: String,
+    /// separated by `;`
+    #[serde(default)]
+    pub mdns_pdl: String,
+    #[serde(default)]
+    pub mdns_color: Option<bool>,
+    #[serde(default)]
+    pub mdns_duplex: Option<bool>,
+    #[serde(default)]
+    pub mdns_uuid: String,
+    #[serde(default)]
+    pub mdns_rp: String,
+    #[serde(default)]
+    pub wsd_endpoint: String,
+    /// separated by `;`
+    #[serde(default)]
+    pub wsd_types: String,
+    /// separated by `;`
+    #[serde(default)]
+    pub wsd_xaddrs: String,
+    #[serde(default)]
+    pub wsd_print: bool,
+    #[serde(default)]
+    pub wsd_scan: bool,
+}
+
+impl RecordEncoder {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format, written: 0 }
+    }
+
+    pub fn header(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Json => "[\n",
+            OutputFormat::Text | OutputFormat::Ndjson | OutputFormat::Csv => "",
+        }
+    }
+
+    pub fn record(&mut self, r: &ScanResult) -> Result<String> {
+        let first = self.written == 0;
+        self.written += 1;
+
+        let s = match self.format {
+            OutputFormat::Text => {
+                let mut line = format!("{}:{}", text_ip(r.ip), r.device);
+                if !r.aliases.is_empty() {
+                    let aliases = r
+                        .aliases
+                        .iter()
+                        .map(IpAddr::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    line.push_str(&format!(" (also on {aliases})"));
+                }
+
+                let name = r.name();
+                if name != r.ip.to_string() {
+                    line.push_str(&format!(" | {name}"));
+                }
+
+                line.push('\n');
+                line
+            }
+            OutputFormat::Json => {
+                let sep = if first { "" } else { ",\n" };
+                format!("{sep}  {}", serde_json::to_string(r)?)
+            }
+            OutputFormat::Ndjson => format!("{}\n", serde_json::to_string(r)?),
+            OutputFormat::Csv => {
+                let mut w = csv::WriterBuilder::new()
+                    .has_headers(first)
+                    .from_writer(vec![]);
+                w.serialize(CsvRow::from(r))?;
+
+                String::from_utf8(w.into_inner()?)?
+            }
+        };
+
+        Ok(s)
+    }
+
+    pub fn footer(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Json if self.written == 0 => "]\n",
+            OutputFormat::Json => "\n]\n",
+            OutputFormat::Text | OutputFormat::Ndjson | OutputFormat::Csv => "",
+        }
+    }
+}
+
+/// IPv6 addresses are bracketed so the `:` before the device stays unambiguous
+fn text_ip(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    }
+}
+
+pub async fn write_results(path: &str, format: OutputFormat, results: &[ScanResult]) -> Result<()> {
+    let mut encoder = RecordEncoder::new(format);
+    let mut content = String::from(encoder.header());
+
+    for r in results {
+        content.push_str(&encoder.record(r)?);
+    }
+
+    content.push_str(encoder.footer());
+
+    let _ = tokio::fs::remove_file(path).await;
+    File::create(path)
+        .await?
+        .write_all(content.as_bytes())
+        .await?;
+
+    Ok(())
+}
+
+/// Reads results back in, text output is too lossy to become [`ScanResult`]s so it isn't supported
+pub fn parse_results(content: &str, format: OutputFormat) -> Result<Vec<ScanResult>> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::from_str(content)?),
+        OutputFormat::Ndjson => content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| Ok(serde_json::from_str(l)?))
+            .collect(),
+        OutputFormat::Csv => csv::Reader::from_reader(content.as_bytes())
+            .deserialize::<CsvRow>()
+            .map(|r| ScanResult::try_from(r?))
+            .collect(),
+        OutputFormat::Text => anyhow::bail!("text output can't be read back into results"),
+    }
+}
+
+impl From<&ScanResult> for CsvRow {
+    fn from(r: &ScanResult) -> Self {
+        let (fingerprint, vendor, model, category, confidence, printer) = match &r.device {
+            NetworkDevice::Identified(d) => (
+                d.fingerprint.clone(),
+                d.vendor.clone(),
+                d.model.clone(),
+                Some(d.category),
+                Some(d.confidence),
+                d.printer.as_ref(),
+            ),
+            NetworkDevice::Unidentified => Default::default(),
+        };
+        let ipp = r.ipp.clone().unwrap_or_default();
+        let snmp = r.snmp.clone().unwrap_or_default();
+        let pjl = r.pjl.clone().unwrap_or_default();
+        let mdns = r.mdns.clone().unwrap_or_default();
+        let wsd = r.wsd.clone().unwrap_or_default();
+
+        Self {
+            ip: r.ip.to_string(),
+            port: r.target.port,
+            scheme: r.target.scheme.to_string(),
+            open: r
+                .open
+                .iter()
+                .map(|t| {
+                    if t.ip == r.ip {
+                        t.to_string()
+                    } else {
+                        format!("{}:{t}", t.ip)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(";"),
+            aliases: r
+                .aliases
+                .iter()
+                .map(IpAddr::to_string)
+                .collect::<Vec<String>>()
+                .join(";"),
+            fingerprint,
+            vendor,
+            model,
+            category,
+            confidence,
+            printer_family: printer.and_then(|p| p.family.clone()).unwrap_or_default(),
+            printer_color: printer.and_then(|p| p.color),
+            printer_mfp: printer.and_then(|p| p.mfp),
+            response_hash: r.response_hash.clone(),
+            seen_at: r.seen_at.to_rfc3339(),
+            cert_subject: r
+                .cert
+                .as_ref()
+                .map(|c| c.subject.clone())
+                .unwrap_or_default(),
+            cert_issuer: r
+                .cert
+                .as_ref()
+                .map(|c| c.issuer.clone())
+                .unwrap_or_default(),
+            cert_sans: r
+                .cert
+                .as_ref()
+                .map(|c| join_list(&c.sans))
+                .unwrap_or_default(),
+            cert_not_before: r
+                .cert
+                .as_ref()
+                .map(|c| c.not_before.to_rfc3339())
+                .unwrap_or_default(),
+            cert_not_after: r
+                .cert
+                .as_ref()
+                .map(|c| c.not_after.to_rfc3339())
+                .unwrap_or_default(),
+            cert_sha256: r
+                .cert
+                .as_ref()
+                .map(|c| c.sha256.clone())
+                .unwrap_or_default(),
+            favicon_mmh3: r.favicon.as_ref().map(|f| f.mmh3),
+            favicon_sha256: r
+                .favicon
+                .as_ref()
+                .map(|f| f.sha256.clone())
+                .unwrap_or_default(),
+            hostname: r.hostname.clone().unwrap_or_default(),
+            web_hostname: r.web_hostname.clone().unwrap_or_default(),
+            mac: r.mac.clone().unwrap_or_default(),
+            oui_vendor: r.oui_vendor.clone().unwrap_or_default(),
+            vendor_mismatch: r.vendor_mismatch,
+            ipp_make_and_model: ipp.make_and_model.clone().unwrap_or_default(),
+            ipp_name: ipp.name.clone().unwrap_or_default(),
+            ipp_location: ipp.location.clone().unwrap_or_default(),
+            ipp_dns_sd_name: ipp.dns_sd_name.clone().unwrap_or_default(),
+            ipp_uuid: ipp.uuid.clone().unwrap_or_default(),
+            snmp_sys_descr: snmp.sys_descr.unwrap_or_default(),
+            snmp_device_descr: snmp.device_descr.unwrap_or_default(),
+            snmp_serial: snmp.serial.unwrap_or_default(),
+            snmp_page_count: snmp.page_count,
+            snmp_supplies: join_list(snmp.supplies.iter().map(|s| {
+                format!(
+                    "{}:{}/{}",
+                    escape(&s.description, ':'),
+                    s.level,
+                    s.max_capacity
+                )
+            })),
+            pjl_id: pjl.id.unwrap_or_default(),
+            pjl_status_code: pjl.status_code,
+            pjl_display: pjl.display.unwrap_or_default(),
+            pjl_online: pjl.online,
+            mdns_instance: mdns.instance,
+            mdns_hostname: mdns.hostname,
+            mdns_services: join_list(&mdns.services),
+            mdns_ty: mdns.ty.unwrap_or_default(),
+            mdns_pdl: join_list(&mdns.pdl),
+            mdns_color: mdns.color,
+            mdns_duplex: mdns.duplex,
+            mdns_uuid: mdns.uuid.unwrap_or_default(),
+            mdns_rp: mdns.rp.unwrap_or_default(),
+            wsd_endpoint: wsd.endpoint,
+            wsd_types: join_list(&wsd.types),
+            wsd_xaddrs: join_list(&wsd.xaddrs),
+            wsd_print: wsd.print,
+            wsd_scan: wsd.scan,
+        }
+    }
+}
+
+impl TryFrom<CsvRow> for ScanResult {
+    type Error = anyhow::Error;
+
+    fn try_from(row: CsvRow) -> Result<Self> {
+        let ip = row.ip.parse::<IpAddr>()?;
+        let scheme = |s: &str| {
+            s.parse::<Scheme>()
+                .map_err(|e| anyhow::anyhow!("invalid scheme {s}: {e}"))
+        };
+
+        let open = row
+            .open
+            .split(';')
+            .filter(|t| !t.is_empty())
+            .map(|t| {
+                let (target_ip, t) = match t.rsplit_once(':') {
+                    Some((target_ip, t)) => (target_ip.parse()?, t),
+                    None => (ip, t),
+                };
+                let (port, s) = t.split_once('/').context("expected port/scheme")?;
+                Ok(Target {
+                    ip: target_ip,
+                    port: port.parse()?,
+                    scheme: scheme(s)?,
+                })
+            })
+            .collect::<Result<Vec<Target>>>()?;
+
+        let text = |v: String| Some(v).filter(|v| !v.is_empty());
+        let list = |v: &str| {
+            split_escaped(v, ';')
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<String>>()
+        };
+        let time = |v: &str| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(v)?.with_timezone(&Utc))
+        };
+        let cert = if row.cert_sha256.is_empty() {
+            None
+        } else {
+            Some(CertInfo {
+                sans: list(&row.cert_sans),
+                not_before: time(&row.cert_not_before)?,
+                not_after: time(&row.cert_not_after)?,
+                subject: row.cert_subject,
+                issuer: row.cert_issuer,
+                sha256: row.cert_sha256,
+            })
+        };
+
+        let favicon = row.favicon_mmh3.map(|mmh3| Favicon {
+            mmh3,
+            sha256: row.favicon_sha256,
+        });
+
+        let ipp = IppInfo {
+            make_and_model: text(row.ipp_make_and_model),
+            name: text(row.ipp_name),
+            location: text(row.ipp_location),
+            dns_sd_name: text(row.ipp_dns_sd_name),
+            uuid: text(row.ipp_uuid),
+        };
+        let answered = ipp.make_and_model.is_some()
+            || ipp.name.is_some()
+            || ipp.location.is_some()
+            || ipp.dns_sd_name.is_some()
+            || ipp.uuid.is_some();
+
+        let supplies = list(&row.snmp_supplies)
+            .iter()
+            .map(|s| {
+                let [description, amounts] = &split_escaped(s, ':')[..] else {
+                    bail!("expected description:level/max");
+                };
+                let (level, max_capacity) =
+                    amounts.split_once('/').context("expected level/max")?;
+                Ok(Supply {
+                    description: description.clone(),
+                    level: level.parse()?,
+                    max_capacity: max_capacity.parse()?,
+                })
+            })
+            .collect::<Result<Vec<Supply>>>()?;
+        let snmp = SnmpInfo {
+            sys_descr: text(row.snmp_sys_descr),
+            device_descr: text(row.snmp_device_descr),
+            serial: text(row.snmp_serial),
+            page_count: row.snmp_page_count,
+            supplies,
+        };
+        let snmp_answered = snmp.sys_descr.is_some() || snmp.is_printer();
+
+        let pjl = PjlInfo {
+            id: text(row.pjl_id),
+            status_code: row.pjl_status_code,
+            display: text(row.pjl_display),
+            online: row.pjl_online,
+        };
+        let pjl_answered = pjl.id.is_some() || pjl.status_code.is_some();
+
+        let mdns = DnsSdInfo {
+            services: list(&row.mdns_services),
+            pdl: list(&row.mdns_pdl),
+            ty: text(row.mdns_ty),
+            color: row.mdns_color,
+            duplex: row.mdns_duplex,
+            uuid: text(row.mdns_uuid),
+            rp: text(row.mdns_rp),
+            instance: row.mdns_instance,
+            hostname: row.mdns_hostname,
+        };
+        let advertised = !mdns.services.is_empty();
+
+        let wsd = WsdInfo {
+            types: list(&row.wsd_types),
+            xaddrs: list(&row.wsd_xaddrs),
+            print: row.wsd_print,
+            scan: row.wsd_scan,
+            endpoint: row.wsd_endpoint,
+        };
+        let probed = !wsd.endpoint.is_empty();
+
+        let device = match row.category {
+            Some(category) if !row.fingerprint.is_empty() => {
+                let printer = (category == Category::Printer).then(|| PrinterModel {
+                    vendor: row.vendor.clone(),
+                    family: text(row.printer_family),
+                    model: row.model.clone(),
+                    color: row.printer_color,
+                    mfp: row.printer_mfp,
+                });
+
+                NetworkDevice::Identified(Device {
+                    fingerprint: row.fingerprint,
+                    vendor: row.vendor,
+                    model: row.model,
+                    category,
+                    confidence: row.confidence.unwrap_or_default(),
+                    printer,
+                })
+            }
+            _ => NetworkDevice::Unidentified,
+        };
+
+        Ok(Self {
+            ip,
+            target: Target {
+                ip,
+                port: row.port,
+                scheme: scheme(&row.scheme)?,
+            },
+            device,
+            open,
+            aliases: row
+                .aliases
+                .split(';')
+                .filter(|a| !a.is_empty())
+                .map(|a| a.parse().with_context(|| format!("invalid alias {a}")))
+                .collect::<Result<Vec<IpAddr>>>()?,
+            response_hash: row.response_hash,
+            seen_at: time(&row.seen_at)?,
+            cert,
+            favicon,
+            hostname: text(row.hostname),
+            web_hostname: text(row.web_hostname),
+            mac: text(row.mac),
+            oui_vendor: text(row.oui_vendor),
+            vendor_mismatch: row.vendor_mismatch,
+            ipp: answered.then_some(ipp),
+            snmp: snmp_answered.then_some(snmp),
+            pjl: pjl_answered.then_some(pjl),
+            mdns: advertised.then_some(mdns),
+            wsd: probed.then_some(wsd),
+            explanation: None,
+            response: None,
+            responses: vec![],
+        })
+    }
+}
+
+/// Lists in a cell are joined with `;`, a `\` in front of a `;` or `\` keeps it in the item
+fn join_list<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> String {
+    items
+        .into_iter()
+        .map(|i| escape(i.as_ref(), ';'))
+        .collect::<Vec<String>>()
+        .join(";")
+}
+
+fn escape(s: &str, sep: char) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || c == sep {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Splits on every `sep` that isn't escaped and removes the escapes
+fn split_escaped(s: &str, sep: char) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        let item = items.last_mut().unwrap();
+        match c {
+            '\\' => item.extend(chars.next()),
+            c if c == sep => items.push(String::new()),
+            c => item.push(c),
+        }
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Every field filled in the way csv can carry it, with separators where text is free
+    fn result() -> ScanResult {
+        let ip = "10.208.2.22".parse().unwrap();
+        let alias = "10.208.9.4".parse().unwrap();
+        let target = Target {
+            ip,
+            port: 443,
+            scheme: Scheme::Https,
+        };
+
+        ScanResult {
+            open: vec![
+                target,
+                Target {
+                    ip: alias,
+                    port: 80,
+                    scheme: Scheme::Http,
+                },
+            ],
+            aliases: vec![alias],
+            response_hash: String::from("ab12"),
+            seen_at: Utc.with_ymd_and_hms(2026, 3, 5, 11, 21, 39).unwrap(),
+            cert: Some(CertInfo {
+                subject: String::from("CN=NPI3F2A1B, O=HP; Inc"),
+                issuer: String::from("CN=NPI3F2A1B, O=HP; Inc"),
+                sans: vec![String::from("npi3f2a1b.local"), String::from("odd;name\\")],
+                not_before: Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+                not_after: Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap(),
+                sha256: String::from("cd34"),
+            }),
+            favicon: Some(Favicon {
+                mmh3: -156908512,
+                sha256: String::from("ef56"),
+            }),
+            hostname: Some(String::from("lib-2f-laser.school.local")),
+            web_hostname: Some(String::from("lib-2f-laser")),
+            mac: Some(String::from("3c:52:82:3f:2a:1b")),
+            oui_vendor: Some(String::from("HP")),
+            vendor_mismatch: false,
+            ipp: Some(IppInfo {
+                make_and_model: Some(String::from("HP LaserJet Pro M402dne")),
+                name: Some(String::from("lib-2f, laser")),
+                location: Some(String::from("Library: 2nd floor")),
+                dns_sd_name: None,
+                uuid: Some(String::from("1c852a4d-b800-1f08-abcd-3c52823f2a1b")),
+            }),
+            snmp: Some(SnmpInfo {
+                sys_descr: Some(String::from("HP ETHERNET MULTI-ENVIRONMENT")),
+                device_descr: Some(String::from("HP LaserJet Pro M402dne")),
+                serial: Some(String::from("PHBHB12345")),
+                page_count: Some(48211),
+                supplies: vec![
+                    Supply {
+                        description: String::from("Black Cartridge HP CF226A"),
+                        level: 40,
+                        max_capacity: 100,
+                    },
+                    Supply {
+                        description: String::from("Toner: black; 2/3 \\ full"),
+                        level: -3,
+                        max_capacity: -2,
+                    },
+                ],
+            }),
+            pjl: Some(PjlInfo {
+                id: Some(String::from("HP LaserJet Pro M402dne")),
+                status_code: Some(10001),
+                display: Some(String::from("Ready")),
+                online: Some(true),
+            }),
+            mdns: Some(DnsSdInfo {
+                instance: String::from("HP LaserJet Pro M402dne (3F2A1B)"),
+                hostname: String::from("NPI3F2A1B.local."),
+                services: vec![
+                    String::from("_ipp._tcp"),
+                    String::from("_pdl-datastream._tcp"),
+                ],
+                ty: Some(String::from("HP LaserJet Pro M402dne")),
+                pdl: vec![
+                    String::from("application/postscript"),
+                    String::from("image/urf"),
+                ],
+                color: Some(false),
+                duplex: Some(true),
+                uuid: Some(String::from("1c852a4d-b800-1f08-abcd-3c52823f2a1b")),
+                rp: Some(String::from("ipp/print")),
+            }),
+            wsd: Some(WsdInfo {
+                endpoint: String::from("urn:uuid:1c852a4d-b800-1f08-abcd-3c52823f2a1b"),
+                types: vec![
+                    String::from("wsdp:Device"),
+                    String::from("wprt:PrintDeviceType"),
+                ],
+                xaddrs: vec![String::from("http://10.208.2.22:3911/")],
+                print: true,
+                scan: false,
+            }),
+            ..ScanResult::new(
+                ip,
+                target,
+                NetworkDevice::Identified(Device {
+                    fingerprint: String::from("hp-laserjet-m402dne"),
+                    vendor: String::from("HP"),
+                    model: String::from("LaserJet M402dne"),
+                    category: Category::Printer,
+                    confidence: 0.675,
+                    printer: Some(PrinterModel::parse("HP LaserJet M402dne")),
+                }),
+            )
+        }
+    }
+
+    fn round_trip(format: OutputFormat, results: &[ScanResult]) -> Vec<ScanResult> {
+        let mut encoder = RecordEncoder::new(format);
+        let mut content = String::from(encoder.header());
+        for r in results {
+            content.push_str(&encoder.record(r).unwrap());
+        }
+        content.push_str(encoder.footer());
+
+        assert_eq!(OutputFormat::detect(&content), format);
+        parse_results(&content, format).unwrap()
+    }
+
+    #[test]
+    fn results_survive_every_format() {
+        let unidentified = ScanResult {
+            seen_at: Utc.with_ymd_and_hms(2026, 3, 5, 11, 21, 40).unwrap(),
+            ..ScanResult::new(
+                "10.208.2.30".parse().unwrap(),
+                Target {
+                    ip: "10.208.2.30".parse().unwrap(),
+                    port: 80,
+                    scheme: Scheme::Http,
+                },
+                NetworkDevice::Unidentified,
+            )
+        };
+        let results = vec![result(), unidentified];
+        let expected = serde_json::to_value(&results).unwrap();
+
+        for format in [OutputFormat::Json, OutputFormat::Ndjson, OutputFormat::Csv] {
+            let parsed = round_trip(format, &results);
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                expected,
+                "{format:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn lists_escape_their_separators() {
+        let items = ["a;b", "c\\", "", "d:e"];
+        assert_eq!(join_list(items), r"a\;b;c\\;;d:e");
+        assert_eq!(split_escaped(&join_list(items), ';'), items);
+    }
+}
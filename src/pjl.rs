@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::{timeout_at, Instant},
+};
+
+pub const RAW_PORT: u16 = 9100;
+
+/// Universal exit language, switches the printer into PJL and ends the job again
+const UEL: &str = "\x1b%-12345X";
+
+/// Vendors whose raw port understands PJL, anything else on 9100 could print the query as text
+pub const PJL_VENDORS: &[&str] = &[
+    "HP",
+    "Lexmark",
+    "Kyocera",
+    "Ricoh",
+    "Xerox",
+    "Brother",
+    "Canon",
+    "Konica Minolta",
+    "Sharp",
+    "Toshiba",
+    "Samsung",
+];
+
+/// Answers are a few hundred bytes, anything past this isn't an answer to our query
+const MAX_RESPONSE: usize = 4096;
+
+/// What a printer answered `@PJL INFO ID` and `@PJL INFO STATUS` with
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PjlInfo {
+    /// usually the make and model, e.x. `HP LaserJet Pro M402dne`
+    pub id: Option<String>,
+    /// e.x. 10001 for ready
+    pub status_code: Option<u32>,
+    /// what the control panel shows
+    pub display: Option<String>,
+    pub online: Option<bool>,
+}
+
+/// Sends nothing but the two queries wrapped in UELs, so there is no job data that could become a page
+pub async fn probe(ip: IpAddr, timeout: Duration) -> Option<PjlInfo> {
+    let deadline = Instant::now() + timeout;
+    let mut stream = timeout_at(deadline, TcpStream::connect(SocketAddr::new(ip, RAW_PORT)))
+        .await
+        .ok()?
+        .ok()?;
+
+    let query = format!("{UEL}@PJL\r\n@PJL INFO ID\r\n@PJL INFO STATUS\r\n{UEL}");
+    timeout_at(deadline, stream.write_all(query.as_bytes()))
+        .await
+        .ok()?
+        .ok()?;
+
+    let mut response = vec![];
+    let mut buf = [0; 1024];
+    // each answer ends with a form feed, stop as soon as both are in instead of waiting out the timeout
+    while response.iter().filter(|b| **b == 0x0c).count() < 2 && response.len() < MAX_RESPONSE {
+        match timeout_at(deadline, stream.read(&mut buf)).await {
+            Ok(Ok(0)) | Ok(Err(_)) | Err(_) => break,
+            Ok(Ok(n)) => response.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let info = parse(&String::from_utf8_lossy(&response));
+    (info.id.is_some() || info.status_code.is_some()).then_some(info)
+}
+
+fn parse(response: &str) -> PjlInfo {
+    let mut info = PjlInfo::default();
+    let mut section = String::new();
+
+    for line in logical_lines(response) {
+        if let Some(command) = line.strip_prefix("@PJL INFO ") {
+            section = command.trim().to_owned();
+            continue;
+        }
+
+        let unquote = |v: &str| v.trim().trim_matches('"').trim().to_owned();
+        match section.as_str() {
+            "ID" if info.id.is_none() && !line.is_empty() => info.id = Some(unquote(&line)),
+            "STATUS" => match line.split_once('=') {
+                Some(("CODE", v)) => info.status_code = v.trim().parse().ok(),
+                Some(("DISPLAY", v)) => info.display = Some(unquote(v)),
+                Some(("ONLINE", v)) => info.online = Some(v.trim().eq_ignore_ascii_case("true")),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Trimmed lines, a quoted value that runs over several lines is joined back into one with spaces.
+/// A quote left open never reaches past the form feed ending its answer
+fn logical_lines(response: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for answer in response.split('\x0c') {
+        let mut open_quote = false;
+
+        for line in answer.split('\n').map(str::trim) {
+            match lines.last_mut() {
+                Some(last) if open_quote => {
+                    if !line.is_empty() {
+                        last.push(' ');
+                        last.push_str(line);
+                    }
+                }
+                _ => lines.push(line.to_owned()),
+            }
+
+            if line.matches('"').count() % 2 == 1 {
+                open_quote = !open_quote;
+            }
+        }
+    }
+
+    lines
+}
+
+impl Display for PjlInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |v: Option<String>| v.unwrap_or_else(|| String::from("unknown"));
+
+        f.write_fmt(format_args!(
+            "{}, status {} {}, online {}",
+            or_unknown(self.id.clone()),
+            or_unknown(self.status_code.map(|c| c.to_string())),
+            or_unknown(self.display.clone()),
+            or_unknown(self.online.map(|o| o.to_string()))
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_id_and_status() {
+        let response = "@PJL INFO ID\r\n\"HP LaserJet Pro M402dne\"\r\n\x0c\
+            @PJL INFO STATUS\r\nCODE=10001\r\nDISPLAY=\"Ready\"\r\nONLINE=TRUE\r\n\x0c";
+
+        let info = parse(response);
+        assert_eq!(info.id.as_deref(), Some("HP LaserJet Pro M402dne"));
+        assert_eq!(info.status_code, Some(10001));
+        assert_eq!(info.display.as_deref(), Some("Ready"));
+        assert_eq!(info.online, Some(true));
+    }
+
+    #[test]
+    fn joins_values_over_several_lines() {
+        // unquoted id, a display message over two lines, indented keys and a status before the id
+        let response = "@PJL INFO STATUS\r\n\tCODE=40038\r\n\tDISPLAY=\"Toner low\r\n\
+            Replace cartridge\"\r\n\tONLINE=FALSE\r\n\x0c\
+            @PJL INFO ID\r\nLexmark MS811\r\n\x0c";
+
+        let info = parse(response);
+        assert_eq!(info.id.as_deref(), Some("Lexmark MS811"));
+        assert_eq!(info.status_code, Some(40038));
+        assert_eq!(info.display.as_deref(), Some("Toner low Replace cartridge"));
+        assert_eq!(info.online, Some(false));
+    }
+
+    #[test]
+    fn ignores_anything_else() {
+        let info = parse("?\r\n@PJL INFO CONFIG\r\nCODE=1\r\n");
+        assert!(info.id.is_none() && info.status_code.is_none());
+        assert!(parse("").display.is_none());
+
+        // a quote that's never closed doesn't swallow the next answer
+        let info = parse(
+            "@PJL INFO ID\r\n\"HP LaserJet 4250\r\n\x0c@PJL INFO STATUS\r\nCODE=10001\r\n\x0c",
+        );
+        assert_eq!(info.id.as_deref(), Some("HP LaserJet 4250"));
+        assert_eq!(info.status_code, Some(10001));
+    }
+}
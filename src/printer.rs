@@ -1,37 +1,57 @@
-use crate::id::devices::Printer;
 use anyhow::{bail, Context, Result};
 use ipp::{
     model::DelimiterTag,
     prelude::{AsyncIppClient, IppAttribute, IppOperationBuilder, Uri},
 };
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+/// What a printer says about itself over IPP
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IppInfo {
+    pub make_and_model: Option<String>,
+    pub name: Option<String>,
+    pub location: Option<String>,
+    /// `printer-dns-sd-name`, what the printer advertises itself as
+    #[serde(default)]
+    pub dns_sd_name: Option<String>,
+    /// `printer-uuid` without the `urn:uuid:` prefix
+    pub uuid: Option<String>,
+}
 
 pub struct CachedPrinter {
     pub ip: String,
-    pub model: Option<Printer>,
+    /// ipps, certificates aren't checked since printers only have self-signed ones
+    pub tls: bool,
     pub attributes: Option<HashMap<String, IppAttribute>>,
     pub supported_extensions: Option<Vec<String>>,
 }
 
 impl CachedPrinter {
-    pub fn new(ip: String, model: Option<Printer>) -> Self {
+    pub fn new(ip: String) -> Self {
         Self {
             ip,
-            model,
+            tls: false,
             attributes: None,
             supported_extensions: None,
         }
     }
 
     pub fn uri(&self) -> Option<Uri> {
-        format!("http://{}", self.ip).parse().ok()
+        let scheme = if self.tls { "https" } else { "http" };
+        format!("{scheme}://{}", self.ip).parse().ok()
     }
 
     pub async fn fetch_attributes(&mut self) -> Result<()> {
         let uri = self.uri().context("bad uri")?;
 
         let operation = IppOperationBuilder::get_printer_attributes(uri.clone()).build();
-        let client = AsyncIppClient::new(uri);
+        let client = AsyncIppClient::builder(uri)
+            .ignore_tls_errors(self.tls)
+            .build();
 
         let resp = client.send(operation).await?;
 
@@ -56,7 +76,7 @@ impl CachedPrinter {
                 .map(|v| {
                     v.as_mime_media_type()
                         .context("failed to convert value to mime media type")
-                        .map(String::clone)
+                        .cloned()
                 })
                 .collect::<Result<Vec<String>>>()
                 .ok();
@@ -66,3 +86,40 @@ impl CachedPrinter {
         Ok(())
     }
 }
+
+impl IppInfo {
+    pub fn from_attributes(attributes: &HashMap<String, IppAttribute>) -> Self {
+        let text = |name: &str| {
+            attributes
+                .get(name)
+                .map(|a| a.value().to_string().trim().to_owned())
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            make_and_model: text(IppAttribute::PRINTER_MAKE_AND_MODEL),
+            name: text(IppAttribute::PRINTER_NAME),
+            location: text(IppAttribute::PRINTER_LOCATION),
+            dns_sd_name: text("printer-dns-sd-name"),
+            uuid: text("printer-uuid").map(|u| {
+                u.strip_prefix("urn:uuid:")
+                    .map_or_else(|| u.clone(), str::to_owned)
+                    .to_lowercase()
+            }),
+        }
+    }
+}
+
+impl Display for IppInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |v: &Option<String>| v.clone().unwrap_or_else(|| String::from("unknown"));
+
+        f.write_fmt(format_args!(
+            "{}, name {}, location {}, uuid {}",
+            or_unknown(&self.make_and_model),
+            or_unknown(&self.name),
+            or_unknown(&self.location),
+            or_unknown(&self.uuid)
+        ))
+    }
+}
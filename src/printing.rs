@@ -3,7 +3,7 @@ use ipp::{
     attribute::IppAttribute,
     model::{DelimiterTag, StatusCode},
     payload::IppPayload,
-    prelude::{AsyncIppClient, IppOperationBuilder, IppRequestResponse, Uri},
+    prelude::{AsyncIppClient, IppOperationBuilder, IppRequestResponse},
     value::IppValue,
 };
 use tokio::fs::File;
@@ -27,7 +27,7 @@ pub async fn print_ipp(args: PrintArgs) -> Result<()> {
 
     let f = args.file.to_lowercase();
 
-    let mut printer = CachedPrinter::new(ip, None);
+    let mut printer = CachedPrinter::new(ip);
     if args.identify_formats {
         println!("Identifying printer formats...");
         printer
@@ -0,0 +1,98 @@
+use crate::{cert::CertInfo, favicon::Favicon};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+/// Everything identification needs from a response, read out of reqwest so it can outlive the request
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CapturedResponse {
+    pub status: u16,
+    /// header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub title: Option<String>,
+    pub body: String,
+    /// peer certificate, only for https
+    pub cert: Option<CertInfo>,
+    /// hashes of `/favicon.ico`, only when probing favicons
+    pub favicon: Option<Favicon>,
+}
+
+impl CapturedResponse {
+    pub async fn capture(res: reqwest::Response) -> Self {
+        let status = res.status().as_u16();
+        let headers = res
+            .headers()
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_lowercase(),
+                    String::from_utf8_lossy(v.as_bytes()).into_owned(),
+                )
+            })
+            .collect();
+        let cert = res
+            .extensions()
+            .get::<reqwest::tls::TlsInfo>()
+            .and_then(|t| t.peer_certificate())
+            .and_then(CertInfo::parse);
+
+        let body = res.text().await.unwrap_or_default();
+
+        Self {
+            cert,
+            ..Self::new(status, headers, body)
+        }
+    }
+
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: String) -> Self {
+        Self {
+            status,
+            headers,
+            title: extract_title(&body),
+            body,
+            cert: None,
+            favicon: None,
+        }
+    }
+
+    /// Hex sha256 of the body, for telling whether a page changed between scans
+    pub fn body_hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.body.as_bytes()))
+    }
+
+    /// Host name shown on the page, e.x. in the network summary of a printer's web UI
+    pub fn hostname(&self) -> Option<String> {
+        static HOSTNAME: OnceLock<Regex> = OnceLock::new();
+        let hostname = HOSTNAME.get_or_init(|| {
+            // the label and the value are usually in neighbouring cells, so any tags in between are skipped
+            Regex::new(
+                r"(?i)host\s*name\s*:?\s*(?:<[^>]*>\s*)*([a-z0-9][a-z0-9-]*(?:\.[a-z0-9-]+)*)\s*<",
+            )
+            .unwrap()
+        });
+
+        Some(hostname.captures(&self.body)?.get(1)?.as_str().to_owned())
+    }
+
+    pub fn headers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn extract_title(body: &str) -> Option<String> {
+    static TITLE: OnceLock<Regex> = OnceLock::new();
+    let title = TITLE.get_or_init(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+    let t = title.captures(body)?.get(1)?.as_str();
+    let t = t.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if t.is_empty() {
+        None
+    } else {
+        Some(t)
+    }
+}
@@ -1,46 +1,208 @@
 use crate::{
-    id::devices::NetworkDevice,
+    archive::Recorder,
+    capture::CaptureDir,
+    cert::CertInfo,
+    correlate, diff,
+    discover::{
+        self,
+        mdns::DnsSdInfo,
+        wsd::{self, WsdInfo},
+    },
+    enrich::Enricher,
+    favicon::{self, Favicon},
+    id::{devices::NetworkDevice, fingerprint::FingerprintDb},
+    inventory::Inventory,
+    output,
+    pjl::{self, PjlInfo, PJL_VENDORS, RAW_PORT},
+    printer::{CachedPrinter, IppInfo},
+    response::CapturedResponse,
+    snmp::{self, SnmpInfo, SnmpVersion, SNMP_PORT},
+    target::{Scheme, Target},
     threads,
     threads::{AppendMessage, ProgressBarMessage},
-    util::{subnet_generator, IpWrapper, ScanError},
-    ScannerArgs,
+    util::ScanError,
+    IdentifyArgs, ProbeArgs, ScannerArgs,
 };
-use anyhow::{bail, Result};
-use rand::seq::SliceRandom;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use ipp::prelude::IppError;
+use rand::Rng;
 use reqwest::{redirect::Policy, Client};
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
-    fs::File,
-    io::AsyncWriteExt,
-    sync::{mpsc, mpsc::UnboundedSender},
+    sync::mpsc,
     task::{self, JoinSet},
+    time::Instant,
 };
 
-pub async fn scan_for_devices(args: ScannerArgs) -> Result<()> {
-    let net = subnet_generator(args.ip_subnet.clone());
-    let hosts = net.into_iter().map(IpWrapper).collect::<Vec<IpWrapper>>();
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScanResult {
+    pub ip: IpAddr,
+    /// the first target that responded, used for identification
+    pub target: Target,
+    pub device: NetworkDevice,
+    pub open: Vec<Target>,
+    /// other ips the same device answered on, merged into this result
+    #[serde(default)]
+    pub aliases: Vec<IpAddr>,
+    /// sha256 of the body that was identified
+    #[serde(default)]
+    pub response_hash: String,
+    pub seen_at: DateTime<Utc>,
+    /// peer certificate of the first https target that responded
+    #[serde(default)]
+    pub cert: Option<CertInfo>,
+    /// hashes of `/favicon.ico` on the identified target, only filled in when probing favicons
+    #[serde(default)]
+    pub favicon: Option<Favicon>,
+    /// from a PTR lookup, only filled in when resolving
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// host name the web UI shows, e.x. on a printer's network summary
+    #[serde(default)]
+    pub web_hostname: Option<String>,
+    /// from the ARP table, only filled in for devices on the local segment
+    #[serde(default)]
+    pub mac: Option<String>,
+    /// who made the network card, by the MAC's prefix
+    #[serde(default)]
+    pub oui_vendor: Option<String>,
+    /// the fingerprint's vendor isn't who made the network card
+    #[serde(default)]
+    pub vendor_mismatch: bool,
+    /// printer attributes, only filled in when probing IPP and the host answered
+    #[serde(default)]
+    pub ipp: Option<IppInfo>,
+    /// Printer MIB, only filled in when probing SNMP and the host answered
+    #[serde(default)]
+    pub snmp: Option<SnmpInfo>,
+    /// answers on the raw port, only filled in when probing PJL and the printer answered
+    #[serde(default)]
+    pub pjl: Option<PjlInfo>,
+    /// what the device advertised, only filled in when it was discovered over DNS-SD
+    #[serde(default)]
+    pub mdns: Option<DnsSdInfo>,
+    /// what the device answered a WS-Discovery probe with, only filled in when it was discovered that way
+    #[serde(default)]
+    pub wsd: Option<WsdInfo>,
+    /// why the device was identified as it was, only filled in when explaining
+    #[serde(skip)]
+    pub explanation: Option<String>,
+    /// the response no fingerprint matched, only kept when saving unidentified hosts
+    #[serde(skip)]
+    pub response: Option<CapturedResponse>,
+    /// every response the host sent, only kept when recording
+    #[serde(skip)]
+    pub responses: Vec<(Target, CapturedResponse)>,
+}
 
-    if hosts.len() < args.threads {
-        bail!("more threads than ips to scan");
+impl ScanResult {
+    /// A result found some other way than probing, with nothing but the device filled in
+    pub fn new(ip: IpAddr, target: Target, device: NetworkDevice) -> Self {
+        Self {
+            ip,
+            target,
+            device,
+            open: vec![target],
+            aliases: vec![],
+            response_hash: String::new(),
+            seen_at: Utc::now(),
+            cert: None,
+            favicon: None,
+            hostname: None,
+            web_hostname: None,
+            mac: None,
+            oui_vendor: None,
+            vendor_mismatch: false,
+            ipp: None,
+            snmp: None,
+            pjl: None,
+            mdns: None,
+            wsd: None,
+            explanation: None,
+            response: None,
+            responses: vec![],
+        }
     }
 
-    let chunks = hosts
-        .chunks(hosts.len() / args.threads)
-        .collect::<Vec<&[IpWrapper]>>();
-
-    let avg = {
-        let chunks_len = chunks.iter().map(|a| a.len());
-        let l = chunks_len.len();
+    /// The most readable name the device is known by, its ip if it has none
+    pub fn name(&self) -> String {
+        self.hostname
+            .clone()
+            .or_else(|| self.ipp.as_ref().and_then(|i| i.dns_sd_name.clone()))
+            .or_else(|| self.web_hostname.clone())
+            .or_else(|| self.cert.as_ref().and_then(CertInfo::hostname))
+            .or_else(|| self.mdns.as_ref().map(|m| m.hostname.clone()))
+            .unwrap_or_else(|| self.ip.to_string())
+    }
+}
 
-        chunks_len.sum::<usize>() / l
-    };
+/// Everything shared between the requests of a scan
+pub struct Prober {
+    client: Client,
+    fingerprints: FingerprintDb,
+    ports: Vec<u16>,
+    schemes: Vec<Scheme>,
+    timeout: Duration,
+    ipp: bool,
+    /// community and version, when probing SNMP
+    snmp: Option<(String, SnmpVersion)>,
+    pjl: bool,
+    favicon: bool,
+    explain: bool,
+    /// keep the response of hosts no fingerprint matched
+    keep_unidentified: bool,
+    /// keep every response, for recording
+    keep_responses: bool,
+}
 
-    println!("Parsed {} ips to scan, start using {} threads chunked into arrays with an avg length of {avg}", hosts.len(), args.threads);
+const IPP_PORT: u16 = 631;
+/// How long `--wsd` listens for answers, devices on the segment answer within a second or two
+const WSD_DURATION: Duration = Duration::from_secs(3);
+/// Where printers serve IPP, CUPS and most vendors use `/ipp/print`, some older ones only the root
+const IPP_PATHS: &[&str] = &["/ipp/print", "/ipp", "/"];
 
-    if hosts.len() > 10 {
-        let sample = hosts
-            .choose_multiple(&mut rand::thread_rng(), 10)
-            .map(|i| i.0.clone())
+pub async fn scan_for_devices(args: ScannerArgs) -> Result<()> {
+    let mut prober = Prober::new(&args.probe, args.explain)?;
+    prober.keep_unidentified = args.save_unidentified.is_some();
+    prober.keep_responses = args.record.is_some();
+    let prober = Arc::new(prober);
+    // read before scanning so a bad path doesn't waste the whole scan
+    let baseline = args.baseline.as_deref().map(diff::load).transpose()?;
+    let inventory = args.inventory.as_deref().map(Inventory::open).transpose()?;
+    let enricher = Enricher::new(&args.enrich)?;
+    let capture = match &args.save_unidentified {
+        Some(dir) => Some(CaptureDir::create(dir).await?),
+        None => None,
+    };
+    let mut saved = 0;
+    let mut recorder = match &args.record {
+        Some(path) => Some(Recorder::create(path).await?),
+        None => None,
+    };
+    let started_at = Utc::now();
+    // answers are collected in the background while the scan runs
+    let wsd = args
+        .wsd
+        .then(|| task::spawn(wsd::probe(Instant::now() + WSD_DURATION)));
+    let hosts = args.ip_subnet.len();
+    let in_flight = args.threads.max(1);
+
+    println!("Parsed {hosts} ips to scan, start with up to {in_flight} requests in flight");
+
+    if hosts > 10 {
+        let mut rng = rand::thread_rng();
+        let sample = (0..10)
+            .filter_map(|_| {
+                let i = rng.gen_range(0..hosts);
+                args.ip_subnet.iter().nth(usize::try_from(i).ok()?)
+            })
+            .map(|i| i.to_string())
             .collect::<Vec<String>>()
             .join(", ");
 
@@ -53,7 +215,7 @@ pub async fn scan_for_devices(args: ScannerArgs) -> Result<()> {
 
         progress_bar = Some((
             sender,
-            task::spawn(threads::progress_bar_thread(hosts.len() as u64, receiver)),
+            task::spawn(threads::progress_bar_thread(hosts, receiver)),
         ));
     }
 
@@ -61,28 +223,117 @@ pub async fn scan_for_devices(args: ScannerArgs) -> Result<()> {
     if args.append_file {
         let (sender, receiver) = mpsc::unbounded_channel();
 
-        appender = Some((sender, task::spawn(threads::append_thread(receiver))));
+        appender = Some((
+            sender,
+            task::spawn(threads::append_thread(
+                args.output.clone(),
+                args.format,
+                receiver,
+            )),
+        ));
     }
 
+    let mut queue = args.ip_subnet.iter().map(IpAddr::from);
     let mut set = JoinSet::new();
-    for servers in chunks {
-        let mut pb = None;
-        if let Some((s, _)) = &progress_bar {
-            pb = Some(s.clone());
-        }
+    let mut devices = vec![];
 
-        let mut ap = None;
-        if let Some((a, _)) = &appender {
-            ap = Some(a.clone());
+    loop {
+        // top the set back up as soon as any request finishes so a slow host never holds up the rest
+        while set.len() < in_flight {
+            let Some(server) = queue.next() else {
+                break;
+            };
+
+            let prober = Arc::clone(&prober);
+            set.spawn(async move {
+                let res = prober.scan_host(server).await;
+                (server, res)
+            });
         }
 
-        set.spawn(scanner_thread(servers.to_vec(), args.clone(), pb, ap));
-    }
+        let Some(res) = set.join_next().await else {
+            break;
+        };
 
-    let mut devices = vec![];
-    while let Some(res) = set.join_next().await {
-        if let Ok(r) = res.unwrap() {
-            devices.push(r);
+        // a panicked probe only loses its own host, the rest of the scan is still worth keeping
+        let (server, res) = match res {
+            Ok(r) => r,
+            Err(e) => {
+                let log = format!("A probe failed: {e}");
+                match &progress_bar {
+                    Some((sender, _)) => {
+                        let _ = sender.send(ProgressBarMessage::Message(log));
+                        let _ = sender.send(ProgressBarMessage::Increment);
+                    }
+                    None => println!("{log}"),
+                }
+                continue;
+            }
+        };
+        let log = match res {
+            Ok(mut r) => {
+                if let (Some(capture), Some(response)) = (&capture, r.response.take()) {
+                    match capture.save(&r, &response).await {
+                        Ok(()) => saved += 1,
+                        Err(e) => println!("Failed to save the response of {server}: {e:#}"),
+                    }
+                }
+                // a recording that can't be written to only loses the recording, not the scan
+                let failed = match &mut recorder {
+                    Some(recorder) => recorder.responses(&mut r).await.err(),
+                    None => None,
+                };
+                if let Some(e) = failed {
+                    println!("Stopped recording, failed to write to it: {e:#}");
+                    recorder = None;
+                }
+                r.responses.clear();
+
+                let open = r
+                    .open
+                    .iter()
+                    .map(Target::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let mut m = format!(
+                    "Valid device type of {} on {} (open {open})",
+                    r.device,
+                    r.target.url()
+                );
+
+                if let Some(explanation) = &r.explanation {
+                    if progress_bar.is_some() {
+                        m = format!("{m}\n{explanation}");
+                    } else {
+                        println!("{m}\n{explanation}");
+                    }
+                }
+
+                if let Some((a, _)) = &appender {
+                    let _ = a.send(AppendMessage::Amendment(Box::new(r.clone())));
+                }
+
+                devices.push(r);
+
+                m
+            }
+            Err(e) => match e {
+                ScanError::Timeout | ScanError::Connection => {
+                    if args.verbose {
+                        format!("{server} {e}")
+                    } else {
+                        String::new()
+                    }
+                }
+                ScanError::OtherError(_) => format!("{server} {e}"),
+            },
+        };
+
+        if let Some((sender, _)) = &progress_bar {
+            if !log.is_empty() {
+                let _ = sender.send(ProgressBarMessage::Message(log));
+            }
+            let _ = sender.send(ProgressBarMessage::Increment);
         }
     }
 
@@ -96,89 +347,393 @@ pub async fn scan_for_devices(args: ScannerArgs) -> Result<()> {
         let _ = t.await;
     }
 
-    let devices = devices.concat();
+    if let Some(wsd) = wsd {
+        match wsd.await {
+            Ok(Ok(found)) => discover::merge_wsd(&mut devices, found),
+            Ok(Err(e)) => println!("WS-Discovery failed: {e:#}"),
+            Err(e) => println!("WS-Discovery failed: {e}"),
+        }
+    }
+
     println!("-- Finished, found {} valid devices --", devices.len());
+    let expired = devices
+        .iter()
+        .filter(|r| r.cert.as_ref().is_some_and(CertInfo::expired))
+        .count();
+    if expired > 0 {
+        println!("{expired} devices have an expired certificate");
+    }
+    if let Some(dir) = &args.save_unidentified {
+        println!("Saved {saved} unidentified responses to {dir}");
+    }
+    // recorded as identified, what enriching adds isn't needed to identify them again
+    if let Some(recorder) = recorder {
+        match recorder.results(&devices).await {
+            Ok(responses) => println!(
+                "Recorded {responses} responses to {}",
+                args.record.as_deref().unwrap_or_default()
+            ),
+            Err(e) => println!("Failed to finish the recording: {e:#}"),
+        }
+    }
+    enricher.enrich(&mut devices).await;
+    // the inventory keeps the history of each ip, so it gets the results from before merging
+    let merged = correlate::merge(devices.clone());
 
-    if !args.append_file {
-        let content = devices
-            .iter()
-            .map(|d| format!("{}:{}", d.0, d.1))
-            .collect::<Vec<String>>()
-            .join("\n");
+    // what was appended during the scan is replaced by the merged and enriched results
+    output::write_results(&args.output, args.format, &merged).await?;
+    println!("Successfully wrote to {}", args.output);
 
-        let _ = tokio::fs::remove_file("./devices.txt").await;
+    if let Some(mut inventory) = inventory {
+        let targets = args.ip_subnet.clone();
+        let devices = devices.clone();
 
-        let _ = File::create("./devices.txt")
-            .await?
-            .write(content.as_bytes())
-            .await?;
+        task::spawn_blocking(move || inventory.record_scan(&targets, started_at, &devices))
+            .await??;
+        println!("Recorded scan in {}", args.inventory.unwrap_or_default());
+    }
 
-        println!("Successfully wrote to ./devices.txt");
+    if let Some(baseline) = baseline {
+        let current = merged.iter().map(diff::Entry::from).collect::<Vec<_>>();
+        print!(
+            "-- Changes since baseline --\n{}",
+            diff::diff(&baseline, &current)
+        );
     }
 
     Ok(())
 }
 
-async fn scanner_thread(
-    servers: Vec<IpWrapper>,
-    args: ScannerArgs,
-    sender: Option<UnboundedSender<ProgressBarMessage>>,
-    appender: Option<UnboundedSender<AppendMessage>>,
-) -> Result<Vec<(IpWrapper, NetworkDevice)>> {
-    let mut client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .redirect(Policy::none())
-        .timeout(Duration::from_millis(args.timeout))
-        .build()?;
-
-    let mut new_servers = vec![];
-
-    for server in servers {
-        let log = match scan(&mut client, &server).await {
-            Ok(t) => {
-                let m = format!("Valid device type of {t} on {}", server.url());
-                if let Some(ref a) = appender {
-                    let _ = a.send(AppendMessage::Amendment(format!("{}:{t}\n", server.0)));
+impl Prober {
+    pub fn new(args: &ProbeArgs, explain: bool) -> Result<Self> {
+        // the client pools connections internally, every request of the scan shares it
+        let timeout = Duration::from_millis(args.timeout);
+        let client = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .tls_info(true)
+            .redirect(Policy::none())
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Self {
+            client,
+            fingerprints: FingerprintDb::with_file(args.fingerprints.as_deref())?,
+            // the ipp probe already asks port 631, over ipp instead of a page
+            ports: args
+                .ports
+                .iter()
+                .copied()
+                .filter(|p| !(args.ipp && *p == IPP_PORT))
+                .collect(),
+            schemes: args.schemes.clone(),
+            timeout,
+            ipp: args.ipp,
+            snmp: args
+                .snmp
+                .then(|| (args.snmp_community.clone(), args.snmp_version)),
+            pjl: args.pjl,
+            favicon: args.favicon,
+            explain,
+            keep_unidentified: false,
+            keep_responses: false,
+        })
+    }
+
+    /// Probes every port at once with each scheme in order, the first port in order that answered is used for identification
+    pub async fn scan_host(&self, ip: IpAddr) -> Result<ScanResult, ScanError> {
+        let mut first = None;
+        let mut open = vec![];
+        let mut error = None;
+        let mut cert = None;
+        let mut responses = vec![];
+
+        // ports are probed at once so a host that doesn't answer only waits out one timeout,
+        // the answers are then taken in port order
+        let mut set = JoinSet::new();
+        for (i, &port) in self.ports.iter().enumerate() {
+            let client = self.client.clone();
+            let schemes = self.schemes.clone();
+
+            set.spawn(async move {
+                let mut error = None;
+                for scheme in schemes {
+                    let target = Target { ip, port, scheme };
+
+                    match scan(&client, &target).await {
+                        Ok(res) => return (i, Ok((target, res))),
+                        // nothing is listening, another scheme on this port won't help
+                        Err(e @ ScanError::Timeout) => {
+                            error.get_or_insert(e);
+                            break;
+                        }
+                        Err(e) => {
+                            error.get_or_insert(e);
+                        }
+                    }
                 }
 
-                new_servers.push((server.clone(), t.clone()));
+                (i, Err(error.unwrap_or(ScanError::Connection)))
+            });
+        }
 
-                m
-            }
-            Err(e) => match e {
-                ScanError::Timeout | ScanError::Connection => {
-                    if args.verbose {
-                        format!("{server} {e}")
-                    } else {
-                        String::new()
+        let mut ports = set.join_all().await;
+        ports.sort_by_key(|(i, _)| *i);
+
+        for (_, port) in ports {
+            match port {
+                Ok((target, res)) => {
+                    if cert.is_none() {
+                        cert.clone_from(&res.cert);
+                    }
+                    if self.keep_responses {
+                        responses.push((target, res.clone()));
+                    }
+                    if first.is_none() {
+                        first = Some((target, res));
                     }
+
+                    open.push(target);
                 }
-                ScanError::OtherError(_) => format!("{server} {e}"),
-            },
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        // snmp and ipp have their own ports, a printer can answer them without any of the http ports
+        let snmp = match &self.snmp {
+            Some((community, version)) => {
+                let target = SocketAddr::new(ip, SNMP_PORT);
+                snmp::probe(target, community, *version, self.timeout)
+                    .await
+                    .ok()
+            }
+            None => None,
         };
 
-        if let Some(ref sender) = sender {
-            if !log.is_empty() {
-                let _ = sender.send(ProgressBarMessage::Message(log));
+        let ipp = if self.ipp {
+            self.probe_ipp(ip).await
+        } else {
+            None
+        };
+        if let Some((t, _)) = &ipp {
+            if !open.contains(t) {
+                open.push(*t);
             }
-            let _ = sender.send(ProgressBarMessage::Increment);
         }
+
+        let snmp_target = Target {
+            ip,
+            port: SNMP_PORT,
+            scheme: Scheme::Snmp,
+        };
+        if snmp.is_some() {
+            open.push(snmp_target);
+        }
+
+        let target = match (&first, &ipp) {
+            (Some((t, _)), _) | (None, Some((t, _))) => *t,
+            (None, None) if snmp.is_some() => snmp_target,
+            (None, None) => return Err(error.unwrap_or(ScanError::Connection)),
+        };
+        let ipp = ipp.map(|(_, info)| info);
+
+        let mut res = first.map(|(_, res)| res);
+        if let Some(res) = &mut res {
+            // a certificate from any port can identify the device, even if the first response was plain http
+            if res.cert.is_none() {
+                res.cert = cert;
+            }
+            if self.favicon {
+                res.favicon = favicon::fetch(&self.client, &target).await;
+                if let Some((_, recorded)) = responses.iter_mut().find(|(t, _)| *t == target) {
+                    recorded.favicon.clone_from(&res.favicon);
+                }
+            }
+        }
+
+        let id = res.as_ref().map(|res| self.fingerprints.identify(&ip, res));
+        let mut device = id.as_ref().map_or(
+            NetworkDevice::Unidentified,
+            NetworkDevice::from_identification,
+        );
+        let explanation = id
+            .as_ref()
+            .filter(|_| self.explain)
+            .map(ToString::to_string);
+
+        let best = id.as_ref().and_then(|id| id.best());
+        let mut fallback = best.is_some_and(|c| c.fingerprint.fallback);
+        device = confirm_reported(device, &mut fallback, ipp.as_ref(), snmp.as_ref(), None);
+
+        // only printers already known to speak pjl, anything else could print the query
+        let mut pjl = None;
+        if self.pjl
+            && device
+                .printer()
+                .is_some_and(|p| PJL_VENDORS.contains(&p.vendor.as_str()))
+        {
+            pjl = pjl::probe(ip, self.timeout).await;
+        }
+
+        if pjl.is_some() {
+            open.push(Target {
+                ip,
+                port: RAW_PORT,
+                scheme: Scheme::Raw,
+            });
+        }
+        device = confirm_reported(device, &mut fallback, None, None, pjl.as_ref());
+
+        Ok(ScanResult {
+            ip,
+            target,
+            device,
+            open,
+            aliases: vec![],
+            response_hash: res
+                .as_ref()
+                .map(CapturedResponse::body_hash)
+                .unwrap_or_default(),
+            seen_at: Utc::now(),
+            cert: res.as_ref().and_then(|r| r.cert.clone()),
+            favicon: res.as_ref().and_then(|r| r.favicon.clone()),
+            hostname: None,
+            web_hostname: res.as_ref().and_then(CapturedResponse::hostname),
+            mac: None,
+            oui_vendor: None,
+            vendor_mismatch: false,
+            ipp,
+            snmp,
+            pjl,
+            mdns: None,
+            wsd: None,
+            explanation,
+            response: res.filter(|_| self.keep_unidentified && best.is_none()),
+            responses,
+        })
     }
 
-    Ok(new_servers)
+    /// Get-Printer-Attributes on the standard IPP port, on every path printers commonly serve it on and then
+    /// over ipps, anything that isn't a printer just won't answer
+    async fn probe_ipp(&self, ip: IpAddr) -> Option<(Target, IppInfo)> {
+        let host = SocketAddr::new(ip, IPP_PORT);
+
+        for scheme in [Scheme::Http, Scheme::Https] {
+            for path in IPP_PATHS {
+                let mut printer = CachedPrinter::new(format!("{host}{path}"));
+                printer.tls = scheme == Scheme::Https;
+
+                let Ok(res) = tokio::time::timeout(self.timeout, printer.fetch_attributes()).await
+                else {
+                    // nothing is listening
+                    return None;
+                };
+
+                match res {
+                    Ok(()) => {
+                        let target = Target {
+                            ip,
+                            port: IPP_PORT,
+                            scheme,
+                        };
+                        let info = printer.attributes.as_ref().map(IppInfo::from_attributes)?;
+                        return Some((target, info));
+                    }
+                    Err(e) => match e.downcast_ref::<IppError>() {
+                        Some(IppError::AsyncClientError(e)) if e.is_connect() => return None,
+                        // the port speaks something else, e.x. only tls, another path won't help
+                        Some(IppError::AsyncClientError(_)) => break,
+                        _ => {}
+                    },
+                }
+            }
+        }
+
+        None
+    }
 }
 
-async fn scan(client: &mut Client, ip: &IpWrapper) -> Result<NetworkDevice, ScanError> {
-    let res = client.get(ip.url()).send().await;
+/// Folds the make and model printers reported about themselves into the fingerprint's guess. ipp's is usually
+/// the cleanest so it goes first, the others only fill gaps
+pub fn confirm_reported(
+    mut device: NetworkDevice,
+    fallback: &mut bool,
+    ipp: Option<&IppInfo>,
+    snmp: Option<&SnmpInfo>,
+    pjl: Option<&PjlInfo>,
+) -> NetworkDevice {
+    let reported = [
+        ("ipp", ipp.and_then(|i| i.make_and_model.as_deref())),
+        ("snmp", snmp.and_then(|s| s.device_descr.as_deref())),
+        ("pjl", pjl.and_then(|p| p.id.as_deref())),
+    ];
+
+    for (source, make_and_model) in reported {
+        if let Some(m) = make_and_model {
+            device = device.confirm_printer(m, source, *fallback);
+            *fallback = false;
+        }
+    }
 
-    let error = match res {
-        Ok(o) => {
-            let text = o.text().await.unwrap_or_default();
-            return Ok(NetworkDevice::from_response(ip, text));
+    device
+}
+
+pub async fn identify_device(args: IdentifyArgs) -> Result<()> {
+    let prober = Prober::new(&args.probe, true)?;
+    let r = prober.scan_host(args.ip).await?;
+
+    let open = r
+        .open
+        .iter()
+        .map(Target::to_string)
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    println!("{} on {} (open {open})", r.device, r.target.url());
+    if let NetworkDevice::Identified(d) = &r.device {
+        println!(
+            "fingerprint {}, confidence {:.2}",
+            d.fingerprint, d.confidence
+        );
+
+        if let Some(p) = &d.printer {
+            println!("printer {p}");
         }
+    }
+
+    if let Some(cert) = &r.cert {
+        println!("cert {cert}");
+    }
+
+    if let Some(favicon) = &r.favicon {
+        println!("favicon {favicon}");
+    }
+
+    if let Some(ipp) = &r.ipp {
+        println!("ipp {ipp}");
+    }
+
+    if let Some(snmp) = &r.snmp {
+        println!("snmp {snmp}");
+    }
+
+    if let Some(pjl) = &r.pjl {
+        println!("pjl {pjl}");
+    }
+
+    print!("{}", r.explanation.unwrap_or_default());
+
+    Ok(())
+}
+
+async fn scan(client: &Client, target: &Target) -> Result<CapturedResponse, ScanError> {
+    let res = client.get(target.url()).send().await;
+
+    let error = match res {
+        Ok(o) => return Ok(CapturedResponse::capture(o).await),
         Err(e) => e,
     };
-
     if error.is_timeout() {
         return Err(ScanError::Timeout);
     }
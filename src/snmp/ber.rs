@@ -0,0 +1,284 @@
+use super::SnmpError;
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const IP_ADDRESS: u8 = 0x40;
+const COUNTER32: u8 = 0x41;
+const GAUGE32: u8 = 0x42;
+const TIME_TICKS: u8 = 0x43;
+const COUNTER64: u8 = 0x46;
+const NO_SUCH_OBJECT: u8 = 0x80;
+const NO_SUCH_INSTANCE: u8 = 0x81;
+const END_OF_MIB_VIEW: u8 = 0x82;
+
+/// Only the pdus a manager sends and the one it gets back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PduType {
+    Get = 0xa0,
+    GetNext = 0xa1,
+    Response = 0xa2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Str(Vec<u8>),
+    Oid(Vec<u32>),
+    /// counters, gauges and time ticks, which only differ in what they count
+    Unsigned(u64),
+    IpAddress([u8; 4]),
+    Null,
+    /// v2c exceptions, `noSuchObject`, `noSuchInstance` and `endOfMibView`
+    Missing,
+}
+
+/// A whole SNMP v1/v2c message, which is the same shape for requests and responses
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// 0 for v1, 1 for v2c
+    pub version: i64,
+    pub community: Vec<u8>,
+    pub pdu: PduType,
+    pub request_id: i64,
+    pub error_status: i64,
+    pub varbinds: Vec<(Vec<u32>, Value)>,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut varbinds = vec![];
+        for (oid, value) in &self.varbinds {
+            let mut vb = encode_oid(oid);
+            vb.extend(encode_value(value));
+            varbinds.extend(tlv(SEQUENCE, &vb));
+        }
+
+        let mut pdu = encode_int(INTEGER, self.request_id);
+        pdu.extend(encode_int(INTEGER, self.error_status));
+        pdu.extend(encode_int(INTEGER, 0));
+        pdu.extend(tlv(SEQUENCE, &varbinds));
+
+        let mut message = encode_int(INTEGER, self.version);
+        message.extend(tlv(OCTET_STRING, &self.community));
+        message.extend(tlv(self.pdu as u8, &pdu));
+
+        tlv(SEQUENCE, &message)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, SnmpError> {
+        let mut outer = Reader(buf);
+        let mut message = Reader(outer.expect(SEQUENCE)?);
+
+        let version = message.int(INTEGER)?;
+        let community = message.expect(OCTET_STRING)?.to_vec();
+
+        let (tag, pdu) = message.tlv()?;
+        let pdu_type = match tag {
+            0xa0 => PduType::Get,
+            0xa1 => PduType::GetNext,
+            0xa2 => PduType::Response,
+            _ => return Err(SnmpError::Malformed("unsupported pdu")),
+        };
+
+        let mut pdu = Reader(pdu);
+        let request_id = pdu.int(INTEGER)?;
+        let error_status = pdu.int(INTEGER)?;
+        let _error_index = pdu.int(INTEGER)?;
+
+        let mut list = Reader(pdu.expect(SEQUENCE)?);
+        let mut varbinds = vec![];
+        while !list.0.is_empty() {
+            let mut vb = Reader(list.expect(SEQUENCE)?);
+            let oid = decode_oid(vb.expect(OBJECT_IDENTIFIER)?)?;
+            varbinds.push((oid, vb.value()?));
+        }
+
+        Ok(Self {
+            version,
+            community,
+            pdu: pdu_type,
+            request_id,
+            error_status,
+            varbinds,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn tlv(&mut self) -> Result<(u8, &'a [u8]), SnmpError> {
+        let truncated = || SnmpError::Malformed("truncated");
+        let (&tag, rest) = self.0.split_first().ok_or_else(truncated)?;
+        let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+
+        let len = if first & 0x80 == 0 {
+            usize::from(first)
+        } else {
+            let octets = usize::from(first & 0x7f);
+            if octets == 0 || octets > 4 || rest.len() < octets {
+                return Err(SnmpError::Malformed("bad length"));
+            }
+
+            let len = rest[..octets]
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+            rest = &rest[octets..];
+            len
+        };
+
+        if rest.len() < len {
+            return Err(truncated());
+        }
+
+        self.0 = &rest[len..];
+        Ok((tag, &rest[..len]))
+    }
+
+    fn expect(&mut self, tag: u8) -> Result<&'a [u8], SnmpError> {
+        match self.tlv()? {
+            (t, content) if t == tag => Ok(content),
+            _ => Err(SnmpError::Malformed("unexpected tag")),
+        }
+    }
+
+    fn int(&mut self, tag: u8) -> Result<i64, SnmpError> {
+        decode_int(self.expect(tag)?)
+    }
+
+    fn value(&mut self) -> Result<Value, SnmpError> {
+        let (tag, content) = self.tlv()?;
+
+        Ok(match tag {
+            INTEGER => Value::Int(decode_int(content)?),
+            OCTET_STRING => Value::Str(content.to_vec()),
+            OBJECT_IDENTIFIER => Value::Oid(decode_oid(content)?),
+            NULL => Value::Null,
+            IP_ADDRESS => Value::IpAddress(
+                content
+                    .try_into()
+                    .map_err(|_| SnmpError::Malformed("bad ip address"))?,
+            ),
+            COUNTER32 | GAUGE32 | TIME_TICKS | COUNTER64 => {
+                if content.len() > 9 {
+                    return Err(SnmpError::Malformed("unsigned too long"));
+                }
+
+                Value::Unsigned(content.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+            }
+            NO_SUCH_OBJECT | NO_SUCH_INSTANCE | END_OF_MIB_VIEW => Value::Missing,
+            _ => return Err(SnmpError::Malformed("unsupported value type")),
+        })
+    }
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+
+    if content.len() < 0x80 {
+        out.push(content.len() as u8);
+    } else {
+        let len = content.len().to_be_bytes();
+        let skip = len.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (len.len() - skip) as u8);
+        out.extend(&len[skip..]);
+    }
+
+    out.extend(content);
+    out
+}
+
+/// Shortest two's complement form, the same for signed and unsigned tags
+fn encode_int(tag: u8, n: i64) -> Vec<u8> {
+    let bytes = n.to_be_bytes();
+    let mut start = 0;
+
+    while start < 7 {
+        let redundant = (bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0);
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+
+    tlv(tag, &bytes[start..])
+}
+
+fn decode_int(content: &[u8]) -> Result<i64, SnmpError> {
+    if content.is_empty() || content.len() > 8 {
+        return Err(SnmpError::Malformed("bad integer"));
+    }
+
+    let sign = if content[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Ok(content
+        .iter()
+        .fold(sign, |acc, b| (acc << 8) | i64::from(*b)))
+}
+
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut content = vec![];
+
+    let (first, rest) = match oid {
+        [a, b, rest @ ..] => (a * 40 + b, rest),
+        [a] => (a * 40, &[][..]),
+        [] => (0, &[][..]),
+    };
+
+    for &n in std::iter::once(&first).chain(rest) {
+        let mut chunk = vec![(n & 0x7f) as u8];
+        let mut n = n >> 7;
+        while n > 0 {
+            chunk.push(0x80 | (n & 0x7f) as u8);
+            n >>= 7;
+        }
+        content.extend(chunk.iter().rev());
+    }
+
+    tlv(OBJECT_IDENTIFIER, &content)
+}
+
+fn decode_oid(content: &[u8]) -> Result<Vec<u32>, SnmpError> {
+    let mut arcs = vec![];
+    let mut n = 0u32;
+
+    for b in content {
+        n = n
+            .checked_mul(128)
+            .ok_or(SnmpError::Malformed("oid arc too large"))?
+            | u32::from(b & 0x7f);
+
+        if b & 0x80 == 0 {
+            arcs.push(n);
+            n = 0;
+        }
+    }
+
+    match arcs.first().copied() {
+        Some(first) => {
+            let (a, b) = if first < 80 {
+                (first / 40, first % 40)
+            } else {
+                (2, first - 80)
+            };
+            arcs.splice(0..1, [a, b]);
+            Ok(arcs)
+        }
+        None => Err(SnmpError::Malformed("empty oid")),
+    }
+}
+
+fn encode_value(value: &Value) -> Vec<u8> {
+    match value {
+        Value::Int(n) => encode_int(INTEGER, *n),
+        Value::Str(s) => tlv(OCTET_STRING, s),
+        Value::Oid(oid) => encode_oid(oid),
+        Value::Unsigned(n) => encode_int(GAUGE32, i64::try_from(*n).unwrap_or(i64::MAX)),
+        Value::IpAddress(ip) => tlv(IP_ADDRESS, ip),
+        Value::Null => tlv(NULL, &[]),
+        Value::Missing => tlv(END_OF_MIB_VIEW, &[]),
+    }
+}
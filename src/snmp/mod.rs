@@ -0,0 +1,462 @@
+mod ber;
+
+use ber::{Message, PduType, Value};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::{net::UdpSocket, time::Instant};
+
+pub const SNMP_PORT: u16 = 161;
+
+const SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+const HR_DEVICE_TYPE: &[u32] = &[1, 3, 6, 1, 2, 1, 25, 3, 2, 1, 2];
+const HR_DEVICE_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 25, 3, 2, 1, 3];
+const HR_DEVICE_PRINTER: &[u32] = &[1, 3, 6, 1, 2, 1, 25, 3, 1, 5];
+const PRT_GENERAL_SERIAL_NUMBER: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 5, 1, 1, 17];
+const PRT_MARKER_LIFE_COUNT: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 10, 2, 1, 4];
+const PRT_MARKER_SUPPLIES_DESCRIPTION: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 11, 1, 1, 6];
+const PRT_MARKER_SUPPLIES_MAX_CAPACITY: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 11, 1, 1, 8];
+const PRT_MARKER_SUPPLIES_LEVEL: &[u32] = &[1, 3, 6, 1, 2, 1, 43, 11, 1, 1, 9];
+
+/// Rows read from a table before giving up, printers have a handful of supplies at most
+const MAX_ROWS: usize = 32;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnmpVersion {
+    V1,
+    V2c,
+}
+
+#[derive(Error, Debug)]
+pub enum SnmpError {
+    #[error("no snmp response")]
+    Timeout,
+    #[error("snmp socket error {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed snmp message, {0}")]
+    Malformed(&'static str),
+}
+
+/// What a host reports over SNMP, mostly from the Printer MIB (RFC 3805)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SnmpInfo {
+    pub sys_descr: Option<String>,
+    /// `hrDeviceDescr` of the printer entry in the host resources table, usually the make and model
+    pub device_descr: Option<String>,
+    pub serial: Option<String>,
+    /// `prtMarkerLifeCount` of the first marker, usually impressions
+    pub page_count: Option<u64>,
+    #[serde(default)]
+    pub supplies: Vec<Supply>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Supply {
+    pub description: String,
+    /// negative values are the MIB's special values, -2 unknown and -3 some remaining
+    pub level: i64,
+    pub max_capacity: i64,
+}
+
+impl Supply {
+    pub fn percent(&self) -> Option<i64> {
+        (self.level >= 0 && self.max_capacity > 0).then(|| self.level * 100 / self.max_capacity)
+    }
+}
+
+impl SnmpInfo {
+    /// Only hosts implementing the Printer MIB, plenty of switches and servers answer `public` too
+    pub fn is_printer(&self) -> bool {
+        self.device_descr.is_some()
+            || self.serial.is_some()
+            || self.page_count.is_some()
+            || !self.supplies.is_empty()
+    }
+}
+
+struct Session {
+    socket: UdpSocket,
+    community: Vec<u8>,
+    version: SnmpVersion,
+    timeout: Duration,
+    request_id: i64,
+}
+
+/// Reads the system description and the Printer MIB, the first request failing means the host doesn't speak SNMP
+/// (or not with this community) and nothing else is tried
+pub async fn probe(
+    target: SocketAddr,
+    community: &str,
+    version: SnmpVersion,
+    timeout: Duration,
+) -> Result<SnmpInfo, SnmpError> {
+    let local = match target.ip() {
+        IpAddr::V4(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+        IpAddr::V6(_) => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    // only datagrams from the target are received from now on
+    socket.connect(target).await?;
+
+    let mut session = Session {
+        socket,
+        community: community.as_bytes().to_vec(),
+        version,
+        timeout,
+        request_id: rand::random::<u16>().into(),
+    };
+
+    let sys_descr = session.get(SYS_DESCR).await?.as_ref().and_then(text);
+
+    let printer_index = session
+        .walk(HR_DEVICE_TYPE)
+        .await?
+        .into_iter()
+        .find(|(_, v)| *v == Value::Oid(HR_DEVICE_PRINTER.to_vec()))
+        .map(|(index, _)| index);
+    let device_descr = match printer_index {
+        Some(index) => session
+            .get(&[HR_DEVICE_DESCR, &index].concat())
+            .await?
+            .as_ref()
+            .and_then(text),
+        None => None,
+    };
+
+    let serial = first(session.walk(PRT_GENERAL_SERIAL_NUMBER).await?, text);
+    let page_count = first(session.walk(PRT_MARKER_LIFE_COUNT).await?, unsigned);
+
+    let levels = session.walk(PRT_MARKER_SUPPLIES_LEVEL).await?;
+    let capacities = session.walk(PRT_MARKER_SUPPLIES_MAX_CAPACITY).await?;
+    let supplies = session
+        .walk(PRT_MARKER_SUPPLIES_DESCRIPTION)
+        .await?
+        .into_iter()
+        .filter_map(|(index, v)| {
+            let column = |rows: &[(Vec<u32>, Value)]| {
+                rows.iter()
+                    .find(|(i, _)| *i == index)
+                    .and_then(|(_, v)| integer(v))
+                    .unwrap_or(-2)
+            };
+
+            Some(Supply {
+                description: text(&v)?,
+                level: column(&levels),
+                max_capacity: column(&capacities),
+            })
+        })
+        .collect();
+
+    Ok(SnmpInfo {
+        sys_descr,
+        device_descr,
+        serial,
+        page_count,
+        supplies,
+    })
+}
+
+impl Session {
+    async fn request(&mut self, pdu: PduType, oid: &[u32]) -> Result<(Vec<u32>, Value), SnmpError> {
+        self.request_id = (self.request_id + 1) % i64::from(i32::MAX);
+        let message = Message {
+            version: match self.version {
+                SnmpVersion::V1 => 0,
+                SnmpVersion::V2c => 1,
+            },
+            community: self.community.clone(),
+            pdu,
+            request_id: self.request_id,
+            error_status: 0,
+            varbinds: vec![(oid.to_vec(), Value::Null)],
+        };
+        self.socket.send(&message.encode()).await?;
+
+        let deadline = Instant::now() + self.timeout;
+        let mut buf = vec![0; 65_535];
+
+        loop {
+            let len = tokio::time::timeout_at(deadline, self.socket.recv(&mut buf))
+                .await
+                .map_err(|_| SnmpError::Timeout)??;
+
+            // late answers to earlier requests or garbage, keep waiting for ours
+            let Ok(res) = Message::decode(&buf[..len]) else {
+                continue;
+            };
+            if res.pdu != PduType::Response || res.request_id != self.request_id {
+                continue;
+            }
+
+            // v1 signals a missing object or the end of a walk with noSuchName instead of an exception value
+            if res.error_status != 0 {
+                return Ok((oid.to_vec(), Value::Missing));
+            }
+
+            return res
+                .varbinds
+                .into_iter()
+                .next()
+                .ok_or(SnmpError::Malformed("no varbinds in response"));
+        }
+    }
+
+    async fn get(&mut self, oid: &[u32]) -> Result<Option<Value>, SnmpError> {
+        let (_, value) = self.request(PduType::Get, oid).await?;
+        Ok(Some(value).filter(|v| *v != Value::Missing))
+    }
+
+    /// Every row under a column, keyed by the index after the column's oid
+    async fn walk(&mut self, column: &[u32]) -> Result<Vec<(Vec<u32>, Value)>, SnmpError> {
+        let mut rows = vec![];
+        let mut oid = column.to_vec();
+
+        while rows.len() < MAX_ROWS {
+            let (next, value) = self.request(PduType::GetNext, &oid).await?;
+            if value == Value::Missing || !next.starts_with(column) || next <= oid {
+                break;
+            }
+
+            rows.push((next[column.len()..].to_vec(), value));
+            oid = next;
+        }
+
+        Ok(rows)
+    }
+}
+
+fn first<T>(rows: Vec<(Vec<u32>, Value)>, f: impl Fn(&Value) -> Option<T>) -> Option<T> {
+    rows.iter().find_map(|(_, v)| f(v))
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Str(s) => Some(
+            String::from_utf8_lossy(s)
+                .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+                .to_owned(),
+        )
+        .filter(|s| !s.is_empty()),
+        _ => None,
+    }
+}
+
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(n) => Some(*n),
+        Value::Unsigned(n) => i64::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+fn unsigned(value: &Value) -> Option<u64> {
+    match value {
+        Value::Int(n) => u64::try_from(*n).ok(),
+        Value::Unsigned(n) => Some(*n),
+        _ => None,
+    }
+}
+
+impl Display for Supply {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (self.percent(), self.level) {
+            (Some(p), _) => f.write_fmt(format_args!("{} {p}%", self.description)),
+            (None, -3) => f.write_fmt(format_args!("{} ok", self.description)),
+            (None, _) => f.write_fmt(format_args!("{} unknown", self.description)),
+        }
+    }
+}
+
+impl Display for SnmpInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |v: Option<String>| v.unwrap_or_else(|| String::from("unknown"));
+        let supplies = self
+            .supplies
+            .iter()
+            .map(Supply::to_string)
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        f.write_fmt(format_args!(
+            "{}, serial {}, pages {}, supplies [{supplies}]",
+            or_unknown(self.device_descr.clone().or_else(|| self.sys_descr.clone())),
+            or_unknown(self.serial.clone()),
+            or_unknown(self.page_count.map(|p| p.to_string()))
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::BTreeMap, ops::Bound};
+
+    fn oid(s: &str) -> Vec<u32> {
+        s.split('.').map(|a| a.parse().unwrap()).collect()
+    }
+
+    fn string(s: &str) -> Value {
+        Value::Str(s.as_bytes().to_vec())
+    }
+
+    /// Roughly what an HP LaserJet answers, with a network card in the host resources table before the printer
+    fn laserjet() -> BTreeMap<Vec<u32>, Value> {
+        [
+            (
+                "1.3.6.1.2.1.1.1.0",
+                string("HP ETHERNET MULTI-ENVIRONMENT,ROM none,JETDIRECT,JD153"),
+            ),
+            ("1.3.6.1.2.1.1.5.0", string("NPI3F2A1B")),
+            (
+                "1.3.6.1.2.1.25.3.2.1.2.1",
+                Value::Oid(oid("1.3.6.1.2.1.25.3.1.4")),
+            ),
+            (
+                "1.3.6.1.2.1.25.3.2.1.2.2",
+                Value::Oid(oid("1.3.6.1.2.1.25.3.1.5")),
+            ),
+            ("1.3.6.1.2.1.25.3.2.1.3.1", string("JetDirect")),
+            (
+                "1.3.6.1.2.1.25.3.2.1.3.2",
+                string("HP LaserJet Pro M402dne"),
+            ),
+            ("1.3.6.1.2.1.43.5.1.1.17.1", string("PHBLB12345\0")),
+            ("1.3.6.1.2.1.43.10.2.1.4.1.1", Value::Unsigned(48_213)),
+            (
+                "1.3.6.1.2.1.43.11.1.1.6.1.1",
+                string("Black Cartridge HP CF226X"),
+            ),
+            ("1.3.6.1.2.1.43.11.1.1.6.1.2", string("Imaging Drum")),
+            ("1.3.6.1.2.1.43.11.1.1.8.1.1", Value::Int(9000)),
+            ("1.3.6.1.2.1.43.11.1.1.8.1.2", Value::Int(-2)),
+            ("1.3.6.1.2.1.43.11.1.1.9.1.1", Value::Int(2250)),
+            ("1.3.6.1.2.1.43.11.1.1.9.1.2", Value::Int(-3)),
+        ]
+        .into_iter()
+        .map(|(o, v)| (oid(o), v))
+        .collect()
+    }
+
+    /// Answers get and get-next from the mib like an agent would, ignoring other communities
+    async fn responder(community: &'static str, mib: BTreeMap<Vec<u32>, Value>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = vec![0; 65_535];
+            loop {
+                let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+                let mut req = Message::decode(&buf[..len]).unwrap();
+                if req.community != community.as_bytes() {
+                    continue;
+                }
+
+                let (oid, _) = req.varbinds.remove(0);
+                let found = match req.pdu {
+                    PduType::Get => mib.get_key_value(&oid),
+                    _ => mib
+                        .range((Bound::Excluded(oid.clone()), Bound::Unbounded))
+                        .next(),
+                };
+
+                let (error_status, varbind) = match (found, req.version) {
+                    (Some((o, v)), _) => (0, (o.clone(), v.clone())),
+                    (None, 0) => (2, (oid, Value::Null)),
+                    (None, _) => (0, (oid, Value::Missing)),
+                };
+
+                let res = Message {
+                    pdu: PduType::Response,
+                    error_status,
+                    varbinds: vec![varbind],
+                    ..req
+                };
+                socket.send_to(&res.encode(), from).await.unwrap();
+            }
+        });
+
+        addr
+    }
+
+    fn assert_laserjet(info: &SnmpInfo) {
+        assert!(info.is_printer());
+        assert_eq!(
+            info.device_descr.as_deref(),
+            Some("HP LaserJet Pro M402dne")
+        );
+        assert_eq!(info.serial.as_deref(), Some("PHBLB12345"));
+        assert_eq!(info.page_count, Some(48_213));
+        assert_eq!(info.supplies.len(), 2);
+        assert_eq!(info.supplies[0].percent(), Some(25));
+        assert_eq!(info.supplies[1].to_string(), "Imaging Drum ok");
+    }
+
+    #[tokio::test]
+    async fn reads_printer_mib_v2c() {
+        let addr = responder("public", laserjet()).await;
+        let info = probe(addr, "public", SnmpVersion::V2c, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        assert!(info.sys_descr.as_ref().unwrap().starts_with("HP ETHERNET"));
+        assert_laserjet(&info);
+    }
+
+    #[tokio::test]
+    async fn reads_printer_mib_v1() {
+        let addr = responder("public", laserjet()).await;
+        let info = probe(addr, "public", SnmpVersion::V1, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        assert_laserjet(&info);
+    }
+
+    #[tokio::test]
+    async fn non_printer_is_not_a_printer() {
+        let mib = [(oid("1.3.6.1.2.1.1.1.0"), string("Cisco IOS Software"))].into();
+        let addr = responder("public", mib).await;
+        let info = probe(addr, "public", SnmpVersion::V2c, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        assert_eq!(info.sys_descr.as_deref(), Some("Cisco IOS Software"));
+        assert!(!info.is_printer());
+    }
+
+    #[tokio::test]
+    async fn wrong_community_times_out() {
+        let addr = responder("private", laserjet()).await;
+        let res = probe(addr, "public", SnmpVersion::V2c, Duration::from_millis(200)).await;
+
+        assert!(matches!(res, Err(SnmpError::Timeout)));
+    }
+
+    #[test]
+    fn ber_round_trips() {
+        let message = Message {
+            version: 1,
+            community: b"public".to_vec(),
+            pdu: PduType::Response,
+            request_id: 1_234_567,
+            error_status: 0,
+            varbinds: vec![
+                (oid("1.3.6.1.4.1.11.2.3.9.4.2.1.1.3.3.0"), Value::Int(-129)),
+                (oid("1.3.6.1.2.1.43.11.1.1.6.1.1"), string(&"x".repeat(300))),
+                (
+                    oid("1.3.6.1.4.1.2385.1.1"),
+                    Value::Unsigned(u64::from(u32::MAX)),
+                ),
+            ],
+        };
+
+        let decoded = Message::decode(&message.encode()).unwrap();
+        assert_eq!(decoded.request_id, message.request_id);
+        assert_eq!(decoded.varbinds, message.varbinds);
+    }
+}
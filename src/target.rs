@@ -0,0 +1,508 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    net::{IpAddr, Ipv4Addr},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Https,
+    Http,
+    /// raw print data, e.x. JetDirect on 9100, never probed over http
+    #[value(skip)]
+    Raw,
+    /// line printer daemon on 515
+    #[value(skip)]
+    Lpd,
+    /// snmp on 161/udp, for devices that only answered that
+    #[value(skip)]
+    Snmp,
+}
+
+/// A single port on a host, probed over one scheme
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub ip: IpAddr,
+    pub port: u16,
+    pub scheme: Scheme,
+}
+
+impl Target {
+    pub fn url(&self) -> String {
+        format!("{}://{}:{}/", self.scheme, self.ip, self.port)
+    }
+}
+
+impl Display for TargetSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scheme::Https => f.write_str("https"),
+            Scheme::Http => f.write_str("http"),
+            Scheme::Raw => f.write_str("raw"),
+            Scheme::Lpd => f.write_str("lpd"),
+            Scheme::Snmp => f.write_str("snmp"),
+        }
+    }
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "https" => Ok(Self::Https),
+            "http" => Ok(Self::Http),
+            "raw" => Ok(Self::Raw),
+            "lpd" => Ok(Self::Lpd),
+            "snmp" => Ok(Self::Snmp),
+            _ => Err(format!("unknown scheme {s}")),
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}/{}", self.port, self.scheme))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TargetSpecError {
+    #[error("empty entry in target spec")]
+    Empty,
+    #[error("invalid address `{0}`")]
+    Address(String),
+    #[error("invalid prefix length in `{0}`, expected 0-32")]
+    Prefix(String),
+    #[error("range `{0}` ends before it starts")]
+    Backwards(String),
+    #[error("wildcards must be the trailing octets in `{0}`")]
+    Wildcard(String),
+    #[error("target spec does not include any addresses")]
+    NoAddresses,
+}
+
+/// Inclusive range of addresses, stored as integers so ranges can be merged and subtracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct AddrRange {
+    start: u32,
+    end: u32,
+}
+
+impl AddrRange {
+    fn len(self) -> u64 {
+        u64::from(self.end - self.start) + 1
+    }
+}
+
+/// Parsed form of `--ip-subnet`, a comma separated list of entries which can be
+/// single ips (`10.208.2.22`), wildcards (`10.208.x.x`), cidr blocks (`10.208.16.0/20`),
+/// dash ranges (`10.208.2.10-10.208.2.90` or `10.208.2.10-90`),
+/// and any of those prefixed with `!` to exclude them.
+#[derive(Debug, Clone)]
+pub struct TargetSpec {
+    ranges: Vec<AddrRange>,
+    /// the spec as it was written
+    source: String,
+}
+
+impl TargetSpec {
+    pub fn len(&self) -> u64 {
+        self.ranges.iter().map(|r| r.len()).sum()
+    }
+
+    /// First and last address of each merged range, in order
+    pub fn ranges(&self) -> impl Iterator<Item = (Ipv4Addr, Ipv4Addr)> + '_ {
+        self.ranges
+            .iter()
+            .map(|r| (Ipv4Addr::from(r.start), Ipv4Addr::from(r.end)))
+    }
+
+    /// Lazily walks every address in the spec, nothing is allocated per address
+    pub fn iter(&self) -> Addresses {
+        Addresses {
+            ranges: self.ranges.clone(),
+            range: 0,
+            next: self.ranges.first().map(|r| u64::from(r.start)),
+        }
+    }
+}
+
+pub struct Addresses {
+    ranges: Vec<AddrRange>,
+    range: usize,
+    next: Option<u64>,
+}
+
+impl Iterator for Addresses {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nth(0)
+    }
+
+    // overridden so skip and step_by can jump through huge ranges without walking them
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        loop {
+            let next = self.next?;
+            let range = self.ranges[self.range];
+            let remaining = u64::from(range.end) - next;
+
+            if (n as u64) <= remaining {
+                let addr = next + n as u64;
+                self.next = if addr < u64::from(range.end) {
+                    Some(addr + 1)
+                } else {
+                    self.advance_range()
+                };
+
+                return Some(Ipv4Addr::from(addr as u32));
+            }
+
+            n -= remaining as usize + 1;
+            self.next = self.advance_range();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let Some(next) = self.next else {
+            return (0, Some(0));
+        };
+
+        let rest = self.ranges[self.range + 1..]
+            .iter()
+            .map(|r| r.len())
+            .sum::<u64>();
+        let len = (u64::from(self.ranges[self.range].end) - next + 1 + rest) as usize;
+
+        (len, Some(len))
+    }
+}
+
+impl Addresses {
+    fn advance_range(&mut self) -> Option<u64> {
+        self.range += 1;
+        self.ranges.get(self.range).map(|r| u64::from(r.start))
+    }
+}
+
+impl FromStr for TargetSpec {
+    type Err = TargetSpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut include = vec![];
+        let mut exclude = vec![];
+
+        for entry in s.split(',').map(str::trim) {
+            match entry.strip_prefix('!') {
+                Some(e) => exclude.push(parse_entry(e.trim())?),
+                None => include.push(parse_entry(entry)?),
+            }
+        }
+
+        let ranges = subtract(merge(include), &merge(exclude));
+        if ranges.is_empty() {
+            return Err(TargetSpecError::NoAddresses);
+        }
+
+        Ok(Self {
+            ranges,
+            source: s.trim().to_owned(),
+        })
+    }
+}
+
+fn parse_entry(entry: &str) -> Result<AddrRange, TargetSpecError> {
+    if entry.is_empty() {
+        return Err(TargetSpecError::Empty);
+    }
+
+    if let Some((addr, prefix)) = entry.split_once('/') {
+        let addr = u32::from(parse_addr(addr)?);
+        let prefix = prefix
+            .parse::<u32>()
+            .ok()
+            .filter(|p| *p <= 32)
+            .ok_or_else(|| TargetSpecError::Prefix(entry.to_owned()))?;
+
+        let host_bits = u32::MAX.checked_shr(prefix).unwrap_or(0);
+        return Ok(AddrRange {
+            start: addr & !host_bits,
+            end: addr | host_bits,
+        });
+    }
+
+    if let Some((start, end)) = entry.split_once('-') {
+        let start = parse_addr(start.trim())?;
+        let end = match end.trim().parse::<u8>() {
+            // shorthand of only the last octet, 10.208.2.10-90
+            Ok(last) => {
+                let [a, b, c, _] = start.octets();
+                Ipv4Addr::new(a, b, c, last)
+            }
+            Err(_) => parse_addr(end.trim())?,
+        };
+
+        if end < start {
+            return Err(TargetSpecError::Backwards(entry.to_owned()));
+        }
+
+        return Ok(AddrRange {
+            start: start.into(),
+            end: end.into(),
+        });
+    }
+
+    if entry.contains('x') {
+        return parse_wildcard(entry);
+    }
+
+    let addr = parse_addr(entry)?.into();
+    Ok(AddrRange {
+        start: addr,
+        end: addr,
+    })
+}
+
+fn parse_addr(addr: &str) -> Result<Ipv4Addr, TargetSpecError> {
+    addr.parse()
+        .map_err(|_| TargetSpecError::Address(addr.to_owned()))
+}
+
+fn parse_wildcard(entry: &str) -> Result<AddrRange, TargetSpecError> {
+    let octets = entry.split('.').collect::<Vec<&str>>();
+    if octets.len() != 4 {
+        return Err(TargetSpecError::Address(entry.to_owned()));
+    }
+
+    let mut start = 0u32;
+    let mut end = 0u32;
+    let mut seen_wildcard = false;
+
+    for octet in octets {
+        let (lo, hi) = if octet == "x" {
+            seen_wildcard = true;
+            (0, 255)
+        } else if seen_wildcard {
+            return Err(TargetSpecError::Wildcard(entry.to_owned()));
+        } else {
+            let o = octet
+                .parse::<u8>()
+                .map_err(|_| TargetSpecError::Address(entry.to_owned()))?;
+            (o, o)
+        };
+
+        start = (start << 8) | u32::from(lo);
+        end = (end << 8) | u32::from(hi);
+    }
+
+    Ok(AddrRange { start, end })
+}
+
+fn merge(mut ranges: Vec<AddrRange>) -> Vec<AddrRange> {
+    ranges.sort();
+
+    let mut merged: Vec<AddrRange> = vec![];
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if u64::from(r.start) <= u64::from(last.end) + 1 => {
+                last.end = last.end.max(r.end);
+            }
+            _ => merged.push(r),
+        }
+    }
+
+    merged
+}
+
+fn subtract(include: Vec<AddrRange>, exclude: &[AddrRange]) -> Vec<AddrRange> {
+    let mut out = vec![];
+
+    for mut r in include {
+        let mut keep = true;
+
+        for e in exclude {
+            if e.end < r.start || e.start > r.end {
+                continue;
+            }
+
+            if e.start > r.start {
+                out.push(AddrRange {
+                    start: r.start,
+                    end: e.start - 1,
+                });
+            }
+
+            if e.end >= r.end {
+                keep = false;
+                break;
+            }
+
+            r.start = e.end + 1;
+        }
+
+        if keep {
+            out.push(r);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(s: &str) -> TargetSpec {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> Ipv4Addr {
+        s.parse().unwrap()
+    }
+
+    fn contains(s: &TargetSpec, ip: Ipv4Addr) -> bool {
+        s.ranges().any(|(start, end)| start <= ip && ip <= end)
+    }
+
+    #[test]
+    fn parses_cidr_and_ranges() {
+        let cidr = spec("10.208.16.7/20");
+        assert_eq!(cidr.len(), 4096);
+        assert!(contains(&cidr, ip("10.208.16.0")));
+        assert!(contains(&cidr, ip("10.208.31.255")));
+        assert!(!contains(&cidr, ip("10.208.32.0")));
+
+        assert_eq!(spec("0.0.0.0/0").len(), 1 << 32);
+        assert_eq!(spec("10.0.0.1/32").len(), 1);
+
+        let range = spec("10.208.2.10-10.208.3.5");
+        assert_eq!(range.len(), 246 + 6);
+        assert!(contains(&range, ip("10.208.2.255")));
+
+        let short = spec("10.208.2.10-90");
+        assert_eq!(short.len(), 81);
+        assert!(contains(&short, ip("10.208.2.90")));
+        assert!(!contains(&short, ip("10.208.2.91")));
+    }
+
+    #[test]
+    fn parses_lists_and_exclusions() {
+        let s = spec("10.0.0.1, 10.0.0.3,10.0.0.2");
+        assert_eq!(s.len(), 3);
+
+        let s = spec("10.208.0.0/16,!10.208.5.0/24, ! 10.208.9.9");
+        assert_eq!(s.len(), 65536 - 256 - 1);
+        assert!(!contains(&s, ip("10.208.5.17")));
+        assert!(!contains(&s, ip("10.208.9.9")));
+        assert!(contains(&s, ip("10.208.9.10")));
+
+        // order doesn't matter, exclusions always win
+        assert_eq!(spec("!10.0.0.2,10.0.0.1-3").len(), 2);
+    }
+
+    #[test]
+    fn wildcards_cover_every_octet() {
+        let s = spec("10.208.x.x");
+        assert_eq!(s.len(), 65536);
+        assert!(contains(&s, ip("10.208.0.0")));
+        assert!(contains(&s, ip("10.208.255.255")));
+
+        assert!(matches!(
+            "10.x.2.x".parse::<TargetSpec>(),
+            Err(TargetSpecError::Wildcard(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        let err = |s: &str| s.parse::<TargetSpec>().unwrap_err();
+
+        assert!(matches!(err("10.0.0.90-10"), TargetSpecError::Backwards(_)));
+        assert!(matches!(
+            err("10.0.0.9-10.0.0.1"),
+            TargetSpecError::Backwards(_)
+        ));
+        assert!(matches!(err("10.0.0.0/33"), TargetSpecError::Prefix(_)));
+        assert!(matches!(err("10.0.0.0/abc"), TargetSpecError::Prefix(_)));
+        assert!(matches!(err("printer"), TargetSpecError::Address(_)));
+        assert!(matches!(err("10.0.0"), TargetSpecError::Address(_)));
+        assert!(matches!(err("10.0.0.256"), TargetSpecError::Address(_)));
+        assert!(matches!(err("10.0.0.1,,10.0.0.2"), TargetSpecError::Empty));
+        assert!(matches!(
+            err("10.0.0.0/24,!10.0.0.x"),
+            TargetSpecError::NoAddresses
+        ));
+    }
+
+    fn addrs(iter: impl Iterator<Item = Ipv4Addr>) -> Vec<String> {
+        iter.map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn walks_merged_ranges_in_order() {
+        let s = spec("10.0.0.5-6,10.0.0.1-2,10.0.0.3,10.0.1.0/31,!10.0.0.2");
+
+        assert_eq!(
+            addrs(s.iter()),
+            ["10.0.0.1", "10.0.0.3", "10.0.0.5", "10.0.0.6", "10.0.1.0", "10.0.1.1"]
+        );
+    }
+
+    #[test]
+    fn nth_crosses_range_boundaries() {
+        // ranges of 2, 1 and 3 addresses
+        let s = spec("10.0.0.1-2,10.0.0.5,10.0.0.8-10");
+
+        for (n, expected) in [
+            (0, "10.0.0.1"),
+            (1, "10.0.0.2"),
+            (2, "10.0.0.5"),
+            (3, "10.0.0.8"),
+            (5, "10.0.0.10"),
+        ] {
+            assert_eq!(s.iter().nth(n).unwrap().to_string(), expected, "nth({n})");
+        }
+        assert_eq!(s.iter().nth(6), None);
+
+        // continues right after the skipped address
+        let mut iter = s.iter();
+        assert_eq!(iter.nth(1).unwrap().to_string(), "10.0.0.2");
+        assert_eq!(iter.nth(1).unwrap().to_string(), "10.0.0.8");
+        assert_eq!(addrs(iter), ["10.0.0.9", "10.0.0.10"]);
+
+        assert_eq!(
+            addrs(s.iter().step_by(2)),
+            ["10.0.0.1", "10.0.0.5", "10.0.0.9"]
+        );
+    }
+
+    #[test]
+    fn len_matches_count() {
+        for s in [
+            "10.0.0.1",
+            "10.0.0.1-2,10.0.0.5,10.0.0.8-10",
+            "10.208.x.x,!10.208.5.0/24,!10.208.200.7",
+            "192.168.0.0/22,192.168.2.0/24",
+        ] {
+            let s = spec(s);
+            let mut iter = s.iter();
+            assert_eq!(iter.size_hint().0 as u64, s.len(), "{s}");
+            assert_eq!(iter.by_ref().count() as u64, s.len(), "{s}");
+            assert_eq!(iter.size_hint(), (0, Some(0)), "{s}");
+
+            let mut iter = s.iter();
+            iter.next();
+            assert_eq!(iter.size_hint().0 as u64, s.len() - 1, "{s}");
+        }
+    }
+}
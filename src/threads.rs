@@ -1,3 +1,8 @@
+use crate::{
+    output::{OutputFormat, RecordEncoder},
+    scanner::ScanResult,
+};
+use anyhow::{Context, Result};
 use indicatif::ProgressBar;
 use tokio::{fs::File, io::AsyncWriteExt, sync::mpsc::UnboundedReceiver};
 
@@ -15,6 +20,8 @@ pub async fn progress_bar_thread(amount: u64, mut rec: UnboundedReceiver<Progres
         match rec.recv().await {
             Some(m) => match m {
                 ProgressBarMessage::Increment => pb.inc(1),
+                // a hidden bar (e.x. output isn't a terminal) swallows println
+                ProgressBarMessage::Message(m) if pb.is_hidden() => println!("{m}"),
                 ProgressBarMessage::Message(m) => pb.println(m),
                 ProgressBarMessage::Close => {
                     cont = false;
@@ -33,22 +40,39 @@ pub async fn progress_bar_thread(amount: u64, mut rec: UnboundedReceiver<Progres
 }
 
 pub enum AppendMessage {
-    Amendment(String),
+    Amendment(Box<ScanResult>),
     Close,
 }
 
-pub async fn append_thread(mut rec: UnboundedReceiver<AppendMessage>) {
-    let _ = tokio::fs::remove_file("./devices.txt").await;
+/// Appends results as they come in, a file that can't be written to only stops the appending, the scan goes on
+pub async fn append_thread(
+    path: String,
+    format: OutputFormat,
+    mut rec: UnboundedReceiver<AppendMessage>,
+) {
+    if let Err(e) = append(&path, format, &mut rec).await {
+        println!("Stopped appending to {path}: {e:#}");
+    }
+}
 
-    let mut f = File::create("./devices.txt").await.unwrap();
+async fn append(
+    path: &str,
+    format: OutputFormat,
+    rec: &mut UnboundedReceiver<AppendMessage>,
+) -> Result<()> {
+    let _ = tokio::fs::remove_file(path).await;
 
-    loop {
-        match rec.recv().await {
-            Some(m) => match m {
-                AppendMessage::Amendment(a) => f.write_all(a.as_bytes()).await.unwrap(),
-                AppendMessage::Close => return,
-            },
-            None => return,
-        }
+    let mut f = File::create(path)
+        .await
+        .with_context(|| format!("failed to create {path}"))?;
+    let mut encoder = RecordEncoder::new(format);
+    f.write_all(encoder.header().as_bytes()).await?;
+
+    while let Some(AppendMessage::Amendment(a)) = rec.recv().await {
+        let record = encoder.record(&a)?;
+        f.write_all(record.as_bytes()).await?;
     }
+
+    f.write_all(encoder.footer().as_bytes()).await?;
+    Ok(())
 }